@@ -0,0 +1,47 @@
+//! Compares converting many small modules with the free functions, which set up wabt
+//! objects for every call, with converting them through a single `ParseContext`.
+//!
+//! Run with `cargo bench --bench context`.
+
+extern crate wabt;
+
+use std::time::{Duration, Instant};
+
+use wabt::{wat2wasm_with_features, Features, ParseContext};
+
+const CONVERSIONS: usize = 10_000;
+
+fn measure<F: FnMut(usize)>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for i in 0..CONVERSIONS {
+        f(i);
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let sources: Vec<String> = (0..CONVERSIONS)
+        .map(|i| format!("(module (func (result i32) i32.const {}))", i))
+        .collect();
+
+    let free_functions = measure(|i| {
+        wat2wasm_with_features(&sources[i], Features::new()).unwrap();
+    });
+    println!(
+        "wat2wasm_with_features: {:?} for {} conversions",
+        free_functions, CONVERSIONS
+    );
+
+    let context = ParseContext::new(Features::new());
+    let with_context = measure(|i| {
+        context.wat2wasm(&sources[i]).unwrap();
+    });
+    println!(
+        "ParseContext::wat2wasm: {:?} for {} conversions",
+        with_context, CONVERSIONS
+    );
+    println!(
+        "speedup: {:.2}x",
+        free_functions.as_secs_f64() / with_context.as_secs_f64()
+    );
+}
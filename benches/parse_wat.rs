@@ -0,0 +1,74 @@
+//! Compares parsing a large module from a borrowed source, which has to be copied, with
+//! parsing it from an owned source, which is moved into the module.
+//!
+//! Run with `cargo bench --bench parse_wat`. Only allocations made on the Rust side are
+//! counted, those made by wabt itself are the same for both.
+
+extern crate wabt;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use wabt::{Features, Module};
+
+struct CountingAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Generate a module with `count` functions, a few megabytes of text for large counts.
+fn generate_wat(count: usize) -> Vec<u8> {
+    let mut wat = String::from("(module\n");
+    for i in 0..count {
+        wat.push_str(&format!(
+            "  (func $f{i} (param $x i32) (result i32)\n    local.get $x\n    i32.const {i}\n    i32.add)\n",
+            i = i
+        ));
+    }
+    wat.push(')');
+    wat.into_bytes()
+}
+
+/// Run `f` and return the time it took and the number of bytes allocated meanwhile.
+fn measure<F: FnOnce()>(f: F) -> (Duration, usize) {
+    let allocated = ALLOCATED.load(Ordering::Relaxed);
+    let start = Instant::now();
+    f();
+    let elapsed = start.elapsed();
+    (elapsed, ALLOCATED.load(Ordering::Relaxed) - allocated)
+}
+
+fn main() {
+    let source = generate_wat(50_000);
+    println!("source size: {} bytes", source.len());
+
+    let (elapsed, allocated) = measure(|| {
+        Module::parse_wat("bench.wat", &source, Features::new()).unwrap();
+    });
+    println!(
+        "parse_wat:       {:>10} bytes allocated, {:?}",
+        allocated, elapsed
+    );
+
+    let (elapsed, allocated) = measure(move || {
+        Module::parse_wat_owned("bench.wat", source, Features::new()).unwrap();
+    });
+    println!(
+        "parse_wat_owned: {:>10} bytes allocated, {:?}",
+        allocated, elapsed
+    );
+}
@@ -10,39 +10,178 @@ extern crate wabt_sys;
 extern crate serde_derive;
 
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::error;
 use std::ffi::{CStr, CString, NulError};
 use std::fmt;
+use std::io;
+use std::ops::{Deref, Range};
 use std::os::raw::{c_int, c_void};
+use std::panic;
 use std::ptr;
 use std::slice;
+use std::str;
+use std::sync::Arc;
+use std::thread;
+use std::vec;
 
 use wabt_sys as ffi;
 
 pub mod script;
 
 /// A structure to represent errors coming out from wabt.
-#[derive(Debug, PartialEq, Eq)]
-pub struct Error(ErrorKind);
+#[derive(Debug)]
+pub struct Error(ErrorKind, Vec<WabtDiagnostic>);
 
 impl Error {
+    fn with_diagnostics(kind: ErrorKind, diagnostics: Vec<WabtDiagnostic>) -> Error {
+        Error(kind, diagnostics)
+    }
+
     /// Returns the `ErrorKind` for this Error.
     pub fn kind(&self) -> &ErrorKind {
         &self.0
     }
+
+    /// Returns a stable numeric code for the `ErrorKind` of this error.
+    ///
+    /// The code only depends on the variant, not on the message, and is part of the public
+    /// API: existing codes never change and new variants get new codes.
+    ///
+    /// | Code | `ErrorKind`            |
+    /// |------|------------------------|
+    /// | 1    | `Nul`                  |
+    /// | 2    | `Deserialize`          |
+    /// | 3    | `Parse`                |
+    /// | 4    | `WriteText`            |
+    /// | 5    | `NonUtf8Result`        |
+    /// | 6    | `WriteBinary`          |
+    /// | 7    | `ResolveNames`         |
+    /// | 8    | `Validate`             |
+    /// | 9    | `GenerateNames`        |
+    /// | 10   | `ApplyNames`           |
+    /// | 11   | `InvalidFunctionIndex` |
+    /// | 12   | `Decompile`            |
+    /// | 13   | `UnknownValueType`     |
+    /// | 14   | `UnknownExport`        |
+    /// | 15   | `DuplicateExport`      |
+    /// | 16   | `Io`                   |
+    /// | 17   | `WriteOffsets`         |
+    /// | 18   | `UnknownExportItem`    |
+    pub fn kind_code(&self) -> u32 {
+        match self.0 {
+            ErrorKind::Nul(_) => 1,
+            ErrorKind::Deserialize(_) => 2,
+            ErrorKind::Parse(_) => 3,
+            ErrorKind::WriteText => 4,
+            ErrorKind::NonUtf8Result => 5,
+            ErrorKind::WriteBinary => 6,
+            ErrorKind::ResolveNames(_) => 7,
+            ErrorKind::Validate(_) => 8,
+            ErrorKind::GenerateNames(_) => 9,
+            ErrorKind::ApplyNames(_) => 10,
+            ErrorKind::InvalidFunctionIndex(_) => 11,
+            ErrorKind::Decompile => 12,
+            ErrorKind::UnknownValueType(_) => 13,
+            ErrorKind::UnknownExport(_) => 14,
+            ErrorKind::DuplicateExport(_) => 15,
+            ErrorKind::Io(_) => 16,
+            ErrorKind::WriteOffsets(_) => 17,
+            ErrorKind::UnknownExportItem(_) => 18,
+        }
+    }
+
+    /// Returns the individual diagnostics reported by wabt.
+    ///
+    /// The message of the error contains all of them formatted together, this allows
+    /// to inspect them one by one. Empty if the error didn't originate from wabt diagnostics.
+    pub fn diagnostics(&self) -> &[WabtDiagnostic] {
+        &self.1
+    }
+
+    /// Returns the diagnostic message reported by wabt, if any.
+    pub fn message(&self) -> Option<&str> {
+        match self.0 {
+            ErrorKind::Deserialize(ref msg)
+            | ErrorKind::Parse(ref msg)
+            | ErrorKind::ResolveNames(ref msg)
+            | ErrorKind::Validate(ref msg) => Some(msg),
+            _ => None,
+        }
+    }
+
+    /// Returns the location in the source text of the first diagnostic, if any.
+    ///
+    /// The location is recovered from the `file:line:col` prefix that wabt puts on
+    /// diagnostics for text sources, so it is only available for errors produced while
+    /// parsing, resolving names or validating a module parsed from text.
+    pub fn location(&self) -> Option<ParseLocation> {
+        match self.0 {
+            ErrorKind::Parse(ref msg)
+            | ErrorKind::ResolveNames(ref msg)
+            | ErrorKind::Validate(ref msg) => ParseLocation::from_message(msg),
+            _ => None,
+        }
+    }
+}
+
+/// Location in the source text that a diagnostic refers to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseLocation {
+    /// Name of the source file, as passed to the parser.
+    pub filename: String,
+    /// Line number, starting from 1.
+    pub line: u32,
+    /// Column number, starting from 1.
+    pub col: u32,
+}
+
+impl ParseLocation {
+    fn from_message(msg: &str) -> Option<ParseLocation> {
+        let prefix = msg.split(": ").next()?;
+        let mut parts = prefix.rsplitn(3, ':');
+        let col = parts.next()?.parse().ok()?;
+        let line = parts.next()?.parse().ok()?;
+        let filename = parts.next()?.to_owned();
+        Some(ParseLocation {
+            filename,
+            line,
+            col,
+        })
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // TODO: A better formatting
-        write!(f, "error: {:?}", self)
+        if let Some(msg) = self.message() {
+            return f.write_str(msg);
+        }
+        match self.0 {
+            ErrorKind::InvalidFunctionIndex(index) => {
+                write!(f, "invalid function index {}", index)
+            }
+            ErrorKind::UnknownValueType(ref name) => write!(f, "unknown value type '{}'", name),
+            ErrorKind::UnknownExport(ref name) => write!(f, "unknown export \"{}\"", name),
+            ErrorKind::DuplicateExport(ref name) => write!(f, "duplicate export \"{}\"", name),
+            ErrorKind::UnknownExportItem(ref name) => {
+                write!(f, "export \"{}\" refers to an unknown item", name)
+            }
+            ErrorKind::GenerateNames(ref msg) => write!(f, "failed to generate names: {}", msg),
+            ErrorKind::ApplyNames(ref msg) => write!(f, "failed to apply names: {}", msg),
+            ErrorKind::Io(ref err) => write!(f, "failed to read source: {}", err),
+            ErrorKind::WriteOffsets(ref msg) => {
+                write!(f, "failed to write instruction offsets: {}", msg)
+            }
+            #[allow(deprecated)]
+            _ => f.write_str(error::Error::description(self)),
+        }
     }
 }
 
 impl error::Error for Error {
     fn description(&self) -> &str {
         match self.0 {
-            ErrorKind::Nul => "string contained nul-byte",
+            ErrorKind::Nul(_) => "string contained nul-byte",
             ErrorKind::Deserialize(_) => "failed to deserialize",
             ErrorKind::Parse(_) => "failed to parse",
             ErrorKind::WriteText => "failed to write text",
@@ -50,16 +189,47 @@ impl error::Error for Error {
             ErrorKind::WriteBinary => "failed to write binary",
             ErrorKind::ResolveNames(_) => "failed to resolve names",
             ErrorKind::Validate(_) => "failed to validate",
+            ErrorKind::GenerateNames(_) => "failed to generate names",
+            ErrorKind::ApplyNames(_) => "failed to apply names",
+            ErrorKind::InvalidFunctionIndex(_) => "invalid function index",
+            ErrorKind::Decompile => "failed to decompile",
+            ErrorKind::UnknownValueType(_) => "unknown value type",
+            ErrorKind::UnknownExport(_) => "unknown export",
+            ErrorKind::DuplicateExport(_) => "duplicate export",
+            ErrorKind::Io(_) => "failed to read source",
+            ErrorKind::WriteOffsets(_) => "failed to write instruction offsets",
+            ErrorKind::UnknownExportItem(_) => "export refers to an unknown item",
+        }
+    }
+
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self.0 {
+            ErrorKind::Nul(ref nul_err) => Some(nul_err),
+            ErrorKind::Io(ref io_err) => Some(io_err),
+            _ => None,
         }
     }
 }
 
+// The diagnostics are just a structured view of the message, so they don't take part
+// in the comparison.
+impl PartialEq for Error {
+    fn eq(&self, other: &Error) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for Error {}
+
 /// ErrorKind describes an error condition from a wasm module operation, as well as the
 /// corresponding error message from `wabt`, if any.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug)]
 pub enum ErrorKind {
     /// Result contained an unexpected null byte.
-    Nul,
+    // clippy takes `std::ffi::NulError` for the `alloc::ffi` re-export, which is newer than
+    // the crate's MSRV, although the type has been available since 1.0.
+    #[allow(clippy::incompatible_msrv)]
+    Nul(NulError),
     /// Error deserializing binary wasm.
     Deserialize(String),
     /// Error parsing textual wasm.
@@ -74,11 +244,75 @@ pub enum ErrorKind {
     ResolveNames(String),
     /// Error validating the wasm module.
     Validate(String),
+    /// Error generating names for the wasm module.
+    GenerateNames(String),
+    /// Error applying names to the wasm module.
+    ApplyNames(String),
+    /// The function index is out of bounds or refers to an imported function.
+    InvalidFunctionIndex(u32),
+    /// Error decompiling a wasm module.
+    Decompile,
+    /// The string doesn't name a value type.
+    UnknownValueType(String),
+    /// The module has no export with this name.
+    UnknownExport(String),
+    /// The module already has an export with this name.
+    DuplicateExport(String),
+    /// Error reading the source.
+    Io(io::Error),
+    /// The offsets of instructions can't be written alongside the text output.
+    WriteOffsets(String),
+    /// The export with this name refers to an item that doesn't exist.
+    UnknownExportItem(String),
+}
+
+// `io::Error` isn't comparable, so I/O errors are equal when their kinds are.
+impl PartialEq for ErrorKind {
+    fn eq(&self, other: &ErrorKind) -> bool {
+        use ErrorKind::*;
+        match (self, other) {
+            (Nul(a), Nul(b)) => a == b,
+            (Deserialize(a), Deserialize(b)) => a == b,
+            (Parse(a), Parse(b)) => a == b,
+            (WriteText, WriteText) => true,
+            (NonUtf8Result, NonUtf8Result) => true,
+            (WriteBinary, WriteBinary) => true,
+            (ResolveNames(a), ResolveNames(b)) => a == b,
+            (Validate(a), Validate(b)) => a == b,
+            (GenerateNames(a), GenerateNames(b)) => a == b,
+            (ApplyNames(a), ApplyNames(b)) => a == b,
+            (InvalidFunctionIndex(a), InvalidFunctionIndex(b)) => a == b,
+            (Decompile, Decompile) => true,
+            (UnknownValueType(a), UnknownValueType(b)) => a == b,
+            (UnknownExport(a), UnknownExport(b)) => a == b,
+            (DuplicateExport(a), DuplicateExport(b)) => a == b,
+            (Io(a), Io(b)) => a.kind() == b.kind(),
+            (WriteOffsets(a), WriteOffsets(b)) => a == b,
+            (UnknownExportItem(a), UnknownExportItem(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for ErrorKind {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::from(ErrorKind::Io(err))
+    }
 }
 
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Error {
+        Error(kind, Vec::new())
+    }
+}
+
+// See `ErrorKind::Nul`.
+#[allow(clippy::incompatible_msrv)]
 impl From<NulError> for Error {
-    fn from(_e: NulError) -> Error {
-        Error(ErrorKind::Nul)
+    fn from(e: NulError) -> Error {
+        Error::from(ErrorKind::Nul(e))
     }
 }
 
@@ -89,21 +323,31 @@ struct Lexer {
 }
 
 impl Lexer {
-    fn new(filename: &str, buffer: &[u8]) -> Result<Lexer, Error> {
-        // TODO: Don't copy.
+    fn new(filename: &str, buffer: Vec<u8>) -> Result<Lexer, Error> {
+        // The lexer doesn't copy the buffer, it keeps a pointer to it. The buffer has to
+        // outlive the lexer rather than just the parse, since diagnostics for the parsed
+        // module quote lines of the source. Moving the buffer into the lexer doesn't move
+        // its contents.
+        let mut lexer = unsafe { Lexer::borrowing(filename, &buffer)? };
+        lexer._buffer = buffer;
+        Ok(lexer)
+    }
+
+    /// Create a lexer for a buffer that it doesn't own.
+    ///
+    /// The lexer keeps a pointer to the buffer, so the caller has to make sure that the
+    /// lexer, and any module parsed with it, is dropped before the buffer.
+    unsafe fn borrowing(filename: &str, buffer: &[u8]) -> Result<Lexer, Error> {
         let filename = CString::new(filename)?;
-        let buffer = buffer.to_owned();
-        let lexer = unsafe {
-            ffi::wabt_new_wast_buffer_lexer(
-                filename.as_ptr(),
-                buffer.as_ptr() as *const c_void,
-                buffer.len(),
-            )
-        };
+        let lexer = ffi::wabt_new_wast_buffer_lexer(
+            filename.as_ptr(),
+            buffer.as_ptr() as *const c_void,
+            buffer.len(),
+        );
 
         Ok(Lexer {
             _filename: filename,
-            _buffer: buffer,
+            _buffer: Vec::new(),
             raw_lexer: lexer,
         })
     }
@@ -117,6 +361,30 @@ impl Drop for Lexer {
     }
 }
 
+/// Severity of a diagnostic reported by wabt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// The diagnostic caused the operation to fail.
+    Error,
+    /// The diagnostic is informational and doesn't affect the result on its own.
+    Warning,
+}
+
+/// Single diagnostic reported by wabt.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WabtDiagnostic {
+    /// Severity of the diagnostic.
+    pub severity: Severity,
+    /// Diagnostic message, without the location prefix.
+    pub message: String,
+    /// Line in the source text, starting from 1. `None` for diagnostics about binaries.
+    pub line: Option<u32>,
+    /// Column in the source text, starting from 1. `None` for diagnostics about binaries.
+    pub column: Option<u32>,
+    /// Offset in the binary. `None` for diagnostics about text sources.
+    pub offset: Option<usize>,
+}
+
 struct Errors {
     raw: *mut ffi::Errors,
 }
@@ -141,6 +409,42 @@ impl Errors {
             WabtBuf { raw_buffer }
         }
     }
+
+    fn clear(&self) {
+        unsafe { ffi::wabt_errors_clear(self.raw) }
+    }
+
+    fn diagnostics(&self) -> Vec<WabtDiagnostic> {
+        let count = unsafe { ffi::wabt_errors_get_count(self.raw) };
+        (0..count)
+            .map(|index| unsafe {
+                let severity = match ffi::wabt_errors_get_level(self.raw, index) {
+                    0 => Severity::Warning,
+                    _ => Severity::Error,
+                };
+                let message = CStr::from_ptr(ffi::wabt_errors_get_message(self.raw, index))
+                    .to_string_lossy()
+                    .into_owned();
+                if ffi::wabt_errors_has_text_location(self.raw, index) != 0 {
+                    WabtDiagnostic {
+                        severity,
+                        message,
+                        line: Some(ffi::wabt_errors_get_line(self.raw, index) as u32),
+                        column: Some(ffi::wabt_errors_get_column(self.raw, index) as u32),
+                        offset: None,
+                    }
+                } else {
+                    WabtDiagnostic {
+                        severity,
+                        message,
+                        line: None,
+                        column: None,
+                        offset: Some(ffi::wabt_errors_get_offset(self.raw, index)),
+                    }
+                }
+            })
+            .collect()
+    }
 }
 
 impl Drop for Errors {
@@ -149,7 +453,266 @@ impl Drop for Errors {
     }
 }
 
+/// Version of wabt this crate was built with, such as `1.0.32`.
+///
+/// Feature support and the JSON output of `wast2json` vary between wabt versions, so this
+/// is worth including in bug reports.
+pub fn wabt_version() -> &'static str {
+    unsafe {
+        CStr::from_ptr(ffi::wabt_version_string())
+            .to_str()
+            .unwrap_or("")
+    }
+}
+
+/// Type of a WebAssembly value.
+///
+/// Parses from and formats as the type names of the text format, e.g. `i32` or `funcref`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    /// 32-bit integer.
+    I32,
+    /// 64-bit integer.
+    I64,
+    /// 32-bit floating point number.
+    F32,
+    /// 64-bit floating point number.
+    F64,
+    /// 128-bit vector.
+    V128,
+    /// Function reference.
+    FuncRef,
+    /// External reference.
+    ExternRef,
+}
+
+impl ValueType {
+    /// Name of the type in the text format.
+    pub fn name(self) -> &'static str {
+        match self {
+            ValueType::I32 => "i32",
+            ValueType::I64 => "i64",
+            ValueType::F32 => "f32",
+            ValueType::F64 => "f64",
+            ValueType::V128 => "v128",
+            ValueType::FuncRef => "funcref",
+            ValueType::ExternRef => "externref",
+        }
+    }
+}
+
+impl ValueType {
+    /// Convert a type code used by wabt, which is the negated binary encoding of the type.
+    fn from_raw(code: i32) -> Result<ValueType, Error> {
+        match code {
+            -0x01 => Ok(ValueType::I32),
+            -0x02 => Ok(ValueType::I64),
+            -0x03 => Ok(ValueType::F32),
+            -0x04 => Ok(ValueType::F64),
+            -0x05 => Ok(ValueType::V128),
+            -0x10 => Ok(ValueType::FuncRef),
+            -0x11 => Ok(ValueType::ExternRef),
+            _ => Err(Error::from(ErrorKind::UnknownValueType(format!(
+                "type code {}",
+                code
+            )))),
+        }
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl str::FromStr for ValueType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ValueType, Error> {
+        match s {
+            "i32" => Ok(ValueType::I32),
+            "i64" => Ok(ValueType::I64),
+            "f32" => Ok(ValueType::F32),
+            "f64" => Ok(ValueType::F64),
+            "v128" => Ok(ValueType::V128),
+            "funcref" => Ok(ValueType::FuncRef),
+            "externref" => Ok(ValueType::ExternRef),
+            _ => Err(Error::from(ErrorKind::UnknownValueType(s.to_owned()))),
+        }
+    }
+}
+
+/// Signature of a function.
+///
+/// Formats as in the text format, e.g. `(param i32 i32) (result i32)`. Empty parameter or
+/// result lists are omitted, so the type of a function without either formats as an empty
+/// string.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FuncType {
+    /// Types of the parameters.
+    pub params: Vec<ValueType>,
+    /// Types of the results.
+    pub results: Vec<ValueType>,
+}
+
+impl FuncType {
+    unsafe fn from_raw(sig: *const ffi::FuncSignature) -> Result<FuncType, Error> {
+        let params = (0..ffi::wabt_func_signature_get_param_count(sig))
+            .map(|index| ValueType::from_raw(ffi::wabt_func_signature_get_param_type(sig, index)))
+            .collect::<Result<_, _>>()?;
+        let results = (0..ffi::wabt_func_signature_get_result_count(sig))
+            .map(|index| ValueType::from_raw(ffi::wabt_func_signature_get_result_type(sig, index)))
+            .collect::<Result<_, _>>()?;
+        Ok(FuncType { params, results })
+    }
+}
+
+impl fmt::Display for FuncType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut separator = "";
+        for &(keyword, types) in &[("param", &self.params), ("result", &self.results)] {
+            if types.is_empty() {
+                continue;
+            }
+            write!(f, "{}({}", separator, keyword)?;
+            for value_type in types {
+                write!(f, " {}", value_type)?;
+            }
+            f.write_str(")")?;
+            separator = " ";
+        }
+        Ok(())
+    }
+}
+
+/// Size limits of a table or a memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Limits {
+    /// Initial size, in elements for tables and in pages for memories.
+    pub initial: u64,
+    /// Maximum size, if any.
+    pub max: Option<u64>,
+}
+
+impl Limits {
+    unsafe fn from_raw(limits: *const ffi::Limits) -> Limits {
+        Limits {
+            initial: ffi::wabt_limits_get_initial(limits),
+            max: if ffi::wabt_limits_has_max(limits) != 0 {
+                Some(ffi::wabt_limits_get_max(limits))
+            } else {
+                None
+            },
+        }
+    }
+}
+
+/// Kind of an imported item, along with its type.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ImportKind {
+    /// Imported function with its signature.
+    Func(FuncType),
+    /// Imported table.
+    Table {
+        /// Type of the table elements.
+        elem_type: ValueType,
+        /// Size limits of the table.
+        limits: Limits,
+    },
+    /// Imported memory.
+    Memory {
+        /// Size limits of the memory, in pages.
+        limits: Limits,
+    },
+    /// Imported global.
+    Global {
+        /// Type of the global.
+        value_type: ValueType,
+        /// Whether the global is mutable.
+        mutable: bool,
+    },
+    /// Imported exception tag.
+    Tag,
+}
+
+/// Import of a module.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Import {
+    /// Name of the module the item is imported from.
+    pub module: String,
+    /// Name of the imported item.
+    pub field: String,
+    /// Kind and type of the imported item.
+    pub kind: ImportKind,
+}
+
+/// Kind of an exported item.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ExportKind {
+    /// Exported function with its signature.
+    Func(FuncType),
+    /// Exported table.
+    Table,
+    /// Exported memory.
+    Memory,
+    /// Exported global.
+    Global,
+    /// Exported exception tag.
+    Tag,
+}
+
+/// Export of a module.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ModuleExport {
+    /// Name the item is exported under.
+    pub name: String,
+    /// Kind of the exported item.
+    pub kind: ExportKind,
+    /// Index of the exported item in its index space, which includes imported items.
+    pub index: u32,
+}
+
+/// Offset expression of an active segment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OffsetExpr {
+    /// Constant 32-bit offset.
+    I32Const(i32),
+    /// Constant 64-bit offset, for 64-bit memories.
+    I64Const(i64),
+    /// Offset read from the global with this index.
+    GlobalGet(u32),
+    /// Any other expression, such as an extended constant expression.
+    Other,
+}
+
+/// Mode of a data segment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DataSegmentMode {
+    /// The segment is copied into a memory during instantiation.
+    Active {
+        /// Index of the memory the segment is copied into.
+        memory: u32,
+        /// Offset in the memory the segment is copied to.
+        offset: OffsetExpr,
+    },
+    /// The segment is only copied into a memory with `memory.init`.
+    Passive,
+}
+
+/// Data segment of a module.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DataSegment {
+    /// Whether the segment is active or passive.
+    pub mode: DataSegmentMode,
+    /// Contents of the segment.
+    pub data: Vec<u8>,
+}
+
 /// Represents which WebAssembly features are enabled in Wabt.
+///
+/// `Features` is `Send` and `Sync`: it is a plain set of flags that is only modified
+/// through `&mut self`.
 pub struct Features {
     raw: *mut ffi::Features,
 }
@@ -179,6 +742,12 @@ impl Features {
         Features { raw }
     }
 
+    pub fn all() -> Features {
+        let mut features = Features::new();
+        features.enable_all();
+        features
+    }
+
     pub fn enable_all(&mut self) {
         self.enable_exceptions();
         self.enable_mutable_globals();
@@ -445,6 +1014,10 @@ impl Drop for Features {
     }
 }
 
+// The wabt features object has no thread affinity and is never mutated through `&self`.
+unsafe impl Send for Features {}
+unsafe impl Sync for Features {}
+
 struct ParseWatResult {
     raw_result: *mut ffi::WabtParseWatResult,
 }
@@ -477,17 +1050,21 @@ fn parse_wat(lexer: &Lexer, features: &Features, errors: &Errors) -> ParseWatRes
 }
 
 struct ReadBinaryResult {
-    raw_result: *mut ffi::WabtReadBinaryResult,
+    raw_result: *mut ffi::WabtReadBinaryIrResult,
 }
 
 impl ReadBinaryResult {
     fn is_ok(&self) -> bool {
-        unsafe { ffi::wabt_read_binary_result_get_result(self.raw_result) == ffi::Result::Ok }
+        unsafe { ffi::wabt_read_binary_ir_result_get_result(self.raw_result) == ffi::Result::Ok }
     }
 
     fn take_module(self) -> Result<*mut ffi::WasmModule, ()> {
         if self.is_ok() {
-            unsafe { Ok(ffi::wabt_read_binary_result_release_module(self.raw_result)) }
+            unsafe {
+                Ok(ffi::wabt_read_binary_ir_result_release_module(
+                    self.raw_result,
+                ))
+            }
         } else {
             Err(())
         }
@@ -497,7 +1074,7 @@ impl ReadBinaryResult {
 impl Drop for ReadBinaryResult {
     fn drop(&mut self) {
         unsafe {
-            ffi::wabt_destroy_read_binary_result(self.raw_result);
+            ffi::wabt_destroy_read_binary_ir_result(self.raw_result);
         }
     }
 }
@@ -511,7 +1088,7 @@ impl Drop for ReadBinaryResult {
 /// ```rust
 /// # extern crate wabt;
 /// # let wabt_buf = wabt::Wat2Wasm::new().convert("(module)").unwrap();
-/// let vec: Vec<u8> = wabt_buf.as_ref().to_vec();
+/// let vec: Vec<u8> = wabt_buf.into_vec();
 /// ```
 ///
 /// Or in `String`:
@@ -519,13 +1096,40 @@ impl Drop for ReadBinaryResult {
 /// ```rust
 /// # extern crate wabt;
 /// # let wabt_buf = wabt::Wat2Wasm::new().convert("(module)").unwrap();
-/// let text = String::from_utf8(wabt_buf.as_ref().to_vec()).unwrap();
+/// let text = String::from_utf8(wabt_buf.into_vec()).unwrap();
 /// ```
 ///
+/// `WabtBuf` is `Send` and `Sync`, since the buffer is never modified after it was
+/// produced by wabt.
+///
+/// Other wrappers of wabt objects are not `Send` nor `Sync` unless documented otherwise,
+/// e.g. [`Module`] is only `Send`.
+///
+/// [`Module`]: struct.Module.html
 pub struct WabtBuf {
     raw_buffer: *mut ffi::OutputBuffer,
 }
 
+impl WabtBuf {
+    fn from_slice(data: &[u8]) -> WabtBuf {
+        let raw_buffer = unsafe { ffi::wabt_new_output_buffer(data.as_ptr(), data.len()) };
+        WabtBuf { raw_buffer }
+    }
+
+    /// Copy the contents of the buffer into a `Vec`, releasing the buffer.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.as_ref().to_vec()
+    }
+
+    /// Write the whole contents of the buffer into `writer`.
+    ///
+    /// Returns the number of bytes written, which is always the length of the buffer.
+    pub fn write_to<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        writer.write_all(self.as_ref())?;
+        Ok(self.len())
+    }
+}
+
 impl AsRef<[u8]> for WabtBuf {
     fn as_ref(&self) -> &[u8] {
         unsafe {
@@ -541,6 +1145,20 @@ impl AsRef<[u8]> for WabtBuf {
     }
 }
 
+impl Deref for WabtBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_ref()
+    }
+}
+
+impl From<WabtBuf> for Vec<u8> {
+    fn from(buf: WabtBuf) -> Vec<u8> {
+        buf.into_vec()
+    }
+}
+
 impl Drop for WabtBuf {
     fn drop(&mut self) {
         unsafe {
@@ -549,6 +1167,10 @@ impl Drop for WabtBuf {
     }
 }
 
+// The buffer is owned exclusively by `WabtBuf` and only read after creation.
+unsafe impl Send for WabtBuf {}
+unsafe impl Sync for WabtBuf {}
+
 struct WriteModuleResult {
     raw_result: *mut ffi::WabtWriteModuleResult,
 }
@@ -567,6 +1189,26 @@ impl WriteModuleResult {
             Err(())
         }
     }
+
+    /// Only valid if the module was written with the log enabled.
+    fn take_wabt_buf_and_log(self) -> Result<(WabtBuf, WabtBuf), ()> {
+        if self.is_ok() {
+            let (raw_buffer, raw_log_buffer) = unsafe {
+                (
+                    ffi::wabt_write_module_result_release_output_buffer(self.raw_result),
+                    ffi::wabt_write_module_result_release_log_output_buffer(self.raw_result),
+                )
+            };
+            Ok((
+                WabtBuf { raw_buffer },
+                WabtBuf {
+                    raw_buffer: raw_log_buffer,
+                },
+            ))
+        } else {
+            Err(())
+        }
+    }
 }
 
 impl Drop for WriteModuleResult {
@@ -575,11 +1217,30 @@ impl Drop for WriteModuleResult {
     }
 }
 
-struct WriteBinaryOptions {
+/// Controls which names are written into the name section of a binary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NameSectionLevel {
+    /// Don't write the name section at all.
+    None,
+    /// Write the name section, but omit the names of function parameters and locals.
+    FunctionsOnly,
+    /// Write the full name section.
+    Full,
+}
+
+/// Options for writing a module as a binary.
+///
+/// These are the same options that [`Wat2Wasm`] provides, so a configuration can be built
+/// once and reused with [`Wat2Wasm::write_binary_options`].
+///
+/// [`Wat2Wasm`]: struct.Wat2Wasm.html
+/// [`Wat2Wasm::write_binary_options`]: struct.Wat2Wasm.html#method.write_binary_options
+#[derive(Clone, Debug)]
+pub struct WriteBinaryOptions {
     log: bool,
     canonicalize_lebs: bool,
     relocatable: bool,
-    write_debug_names: bool,
+    name_section: NameSectionLevel,
 }
 
 impl Default for WriteBinaryOptions {
@@ -588,14 +1249,59 @@ impl Default for WriteBinaryOptions {
             log: false,
             canonicalize_lebs: true,
             relocatable: false,
-            write_debug_names: false,
+            name_section: NameSectionLevel::None,
         }
     }
 }
 
-struct WriteTextOptions {
+impl WriteBinaryOptions {
+    /// Record a log of the binary writer.
+    ///
+    /// `false` by default.
+    pub fn log(&mut self, log: bool) -> &mut WriteBinaryOptions {
+        self.log = log;
+        self
+    }
+
+    /// Write canonicalized LEB128 for var ints.
+    ///
+    /// Set this to `false` to write all LEB128 sizes as 5-bytes instead of their minimal size.
+    /// `true` by default.
+    pub fn canonicalize_lebs(&mut self, canonicalize_lebs: bool) -> &mut WriteBinaryOptions {
+        self.canonicalize_lebs = canonicalize_lebs;
+        self
+    }
+
+    /// Create a relocatable wasm binary.
+    ///
+    /// `false` by default.
+    pub fn relocatable(&mut self, relocatable: bool) -> &mut WriteBinaryOptions {
+        self.relocatable = relocatable;
+        self
+    }
+
+    /// Select which names are written to the name section.
+    ///
+    /// `NameSectionLevel::None` by default.
+    pub fn name_section(&mut self, name_section: NameSectionLevel) -> &mut WriteBinaryOptions {
+        self.name_section = name_section;
+        self
+    }
+}
+
+/// Options for writing a module in the text format.
+///
+/// These are the same options that [`Wasm2Wat`] provides, see there for examples of their
+/// effect. A configuration can be built once and reused with
+/// [`Wasm2Wat::write_text_options`].
+///
+/// [`Wasm2Wat`]: struct.Wasm2Wat.html
+/// [`Wasm2Wat::write_text_options`]: struct.Wasm2Wat.html#method.write_text_options
+#[derive(Clone, Debug)]
+pub struct WriteTextOptions {
     fold_exprs: bool,
     inline_export: bool,
+    explicit_indices: bool,
 }
 
 impl Default for WriteTextOptions {
@@ -603,14 +1309,44 @@ impl Default for WriteTextOptions {
         WriteTextOptions {
             fold_exprs: false,
             inline_export: false,
+            explicit_indices: false,
         }
     }
 }
 
+impl WriteTextOptions {
+    /// Write folded expressions where possible.
+    ///
+    /// `false` by default.
+    pub fn fold_exprs(&mut self, fold_exprs: bool) -> &mut WriteTextOptions {
+        self.fold_exprs = fold_exprs;
+        self
+    }
+
+    /// Write all exports inline.
+    ///
+    /// `false` by default.
+    pub fn inline_export(&mut self, inline_export: bool) -> &mut WriteTextOptions {
+        self.inline_export = inline_export;
+        self
+    }
+
+    /// Write the index comment, such as `(;0;)`, for every item, even if it has a name.
+    ///
+    /// `false` by default.
+    pub fn explicit_indices(&mut self, explicit_indices: bool) -> &mut WriteTextOptions {
+        self.explicit_indices = explicit_indices;
+        self
+    }
+}
+
 /// Options for reading read binary.
 pub struct ReadBinaryOptions {
     features: Features,
     read_debug_names: bool,
+    read_custom_sections: bool,
+    stop_on_first_error: bool,
+    fail_on_custom_section_error: bool,
 }
 
 impl Default for ReadBinaryOptions {
@@ -618,10 +1354,54 @@ impl Default for ReadBinaryOptions {
         ReadBinaryOptions {
             features: Features::new(),
             read_debug_names: false,
+            read_custom_sections: false,
+            stop_on_first_error: true,
+            fail_on_custom_section_error: true,
         }
     }
 }
 
+impl ReadBinaryOptions {
+    /// Keep the contents of all custom sections, see [`Module::custom_sections`].
+    ///
+    /// `false` by default.
+    ///
+    /// [`Module::custom_sections`]: struct.Module.html#method.custom_sections
+    pub fn read_custom_sections(&mut self, read_custom_sections: bool) -> &mut ReadBinaryOptions {
+        self.read_custom_sections = read_custom_sections;
+        self
+    }
+
+    /// Stop reading at the first error.
+    ///
+    /// Set this to `false` to keep reading after an error, so that all of the errors are
+    /// reported. Reading still fails if there was any error.
+    ///
+    /// `true` by default.
+    pub fn stop_on_first_error(&mut self, stop_on_first_error: bool) -> &mut ReadBinaryOptions {
+        self.stop_on_first_error = stop_on_first_error;
+        self
+    }
+
+    /// Fail if a custom section that wabt understands, such as the name section, is
+    /// malformed.
+    ///
+    /// Set this to `false` to skip malformed custom sections instead, which helps with
+    /// binaries from the wild whose custom sections are corrupt. The errors are still
+    /// reported as warnings, see [`Module::warnings`], but reading succeeds.
+    ///
+    /// `true` by default.
+    ///
+    /// [`Module::warnings`]: struct.Module.html#method.warnings
+    pub fn fail_on_custom_section_error(
+        &mut self,
+        fail_on_custom_section_error: bool,
+    ) -> &mut ReadBinaryOptions {
+        self.fail_on_custom_section_error = fail_on_custom_section_error;
+        self
+    }
+}
+
 struct ParseWastResult {
     raw_result: *mut ffi::WabtParseWastResult,
 }
@@ -665,7 +1445,7 @@ impl Script {
         source: S,
         features: Features,
     ) -> Result<Script, Error> {
-        let lexer = Lexer::new(filename, source.as_ref())?;
+        let lexer = Lexer::new(filename, source.as_ref().to_owned())?;
         let errors = Errors::new();
         match parse_wast(&lexer, &features, &errors).take_script() {
             Ok(raw_script) => Ok(Script {
@@ -675,7 +1455,10 @@ impl Script {
             }),
             Err(()) => {
                 let msg = String::from_utf8_lossy(errors.format_text(&lexer).as_ref()).to_string();
-                Err(Error(ErrorKind::Parse(msg)))
+                Err(Error::with_diagnostics(
+                    ErrorKind::Parse(msg),
+                    errors.diagnostics(),
+                ))
             }
         }
     }
@@ -687,7 +1470,10 @@ impl Script {
             if result == ffi::Result::Error {
                 let msg =
                     String::from_utf8_lossy(errors.format_text(&self.lexer).as_ref()).to_string();
-                return Err(Error(ErrorKind::ResolveNames(msg)));
+                return Err(Error::with_diagnostics(
+                    ErrorKind::ResolveNames(msg),
+                    errors.diagnostics(),
+                ));
             }
         }
         Ok(())
@@ -700,7 +1486,10 @@ impl Script {
             if result == ffi::Result::Error {
                 let msg =
                     String::from_utf8_lossy(errors.format_text(&self.lexer).as_ref()).to_string();
-                return Err(Error(ErrorKind::Validate(msg)));
+                return Err(Error::with_diagnostics(
+                    ErrorKind::Validate(msg),
+                    errors.diagnostics(),
+                ));
             }
         }
         Ok(())
@@ -725,10 +1514,17 @@ impl Script {
 }
 
 /// WebAssembly module.
+///
+/// `Module` is `Send`, so a module can be parsed on one thread and converted on another.
+/// It is not `Sync` though: formatting diagnostics for a module parsed from text updates
+/// the state of its lexer, even through `&self`.
 pub struct Module {
     raw_module: *mut ffi::WasmModule,
     lexer: Option<Lexer>,
-    features: Features,
+    // Shared with the `ParseContext` the module was parsed with, if any.
+    features: Arc<Features>,
+    custom_sections: Vec<(String, Vec<u8>)>,
+    warnings: Vec<WabtDiagnostic>,
 }
 
 impl Module {
@@ -738,24 +1534,76 @@ impl Module {
         source: S,
         features: Features,
     ) -> Result<Module, Error> {
-        let lexer = Lexer::new(filename, source.as_ref())?;
-        let errors = Errors::new();
-        match parse_wat(&lexer, &features, &errors).take_module() {
+        Module::parse_wat_with_errors(
+            filename,
+            source.as_ref().to_owned(),
+            Arc::new(features),
+            &Errors::new(),
+        )
+    }
+
+    /// Parse source in WebAssembly text format from a string.
+    ///
+    /// This is [`parse_wat`] with the filename `<buffer>`, for sources that don't come
+    /// from a file. Use [`parse_wat`] to have the real filename in diagnostics.
+    ///
+    /// [`parse_wat`]: #method.parse_wat
+    pub fn parse_wat_str(source: &str, features: Features) -> Result<Module, Error> {
+        Module::parse_wat("<buffer>", source, features)
+    }
+
+    /// Parse source in WebAssembly text format, taking ownership of the source.
+    ///
+    /// The module has to keep the source around, so [`parse_wat`] makes a copy of it. This
+    /// avoids the copy for callers that already own the source, which matters for large
+    /// generated modules.
+    ///
+    /// [`parse_wat`]: #method.parse_wat
+    pub fn parse_wat_owned(
+        filename: &str,
+        source: Vec<u8>,
+        features: Features,
+    ) -> Result<Module, Error> {
+        Module::parse_wat_with_errors(filename, source, Arc::new(features), &Errors::new())
+    }
+
+    fn parse_wat_with_errors(
+        filename: &str,
+        source: Vec<u8>,
+        features: Arc<Features>,
+        errors: &Errors,
+    ) -> Result<Module, Error> {
+        let lexer = Lexer::new(filename, source)?;
+        Module::parse_wat_with_lexer(lexer, features, errors)
+    }
+
+    fn parse_wat_with_lexer(
+        lexer: Lexer,
+        features: Arc<Features>,
+        errors: &Errors,
+    ) -> Result<Module, Error> {
+        errors.clear();
+        match parse_wat(&lexer, &features, errors).take_module() {
             Ok(module) => Ok(Module {
                 raw_module: module,
                 features,
                 lexer: Some(lexer),
+                custom_sections: Vec::new(),
+                warnings: errors.diagnostics(),
             }),
             Err(()) => {
                 let msg = String::from_utf8_lossy(errors.format_text(&lexer).as_ref()).to_string();
-                Err(Error(ErrorKind::Parse(msg)))
+                Err(Error::with_diagnostics(
+                    ErrorKind::Parse(msg),
+                    errors.diagnostics(),
+                ))
             }
         }
     }
 
     /// Read WebAssembly binary.
     ///
-    /// `read_binary` doesn't do any validation. If you want to validate, you can the module you can
+    /// `read_binary` doesn't do any validation. If you want to validate the module, you can
     /// call [`validate`].
     ///
     /// [`validate`]: #method.validate
@@ -763,35 +1611,119 @@ impl Module {
         wasm: S,
         options: &ReadBinaryOptions,
     ) -> Result<Module, Error> {
-        let errors = Errors::new();
+        Module::read_binary_with_errors(
+            wasm.as_ref(),
+            options,
+            Arc::new(options.features.clone()),
+            &Errors::new(),
+        )
+    }
+
+    // Reads with `features` rather than the features of `options`, so that the module can
+    // share them.
+    fn read_binary_with_errors(
+        wasm: &[u8],
+        options: &ReadBinaryOptions,
+        features: Arc<Features>,
+        errors: &Errors,
+    ) -> Result<Module, Error> {
+        errors.clear();
         let result = {
-            let wasm = wasm.as_ref();
             let raw_result = unsafe {
-                ffi::wabt_read_binary(
+                ffi::wabt_read_binary_ir(
                     wasm.as_ptr(),
                     wasm.len(),
                     options.read_debug_names as c_int,
-                    options.features.raw,
+                    options.stop_on_first_error as c_int,
+                    options.fail_on_custom_section_error as c_int,
+                    features.raw,
                     errors.raw,
                 )
             };
             ReadBinaryResult { raw_result }
         };
         match result.take_module() {
-            Ok(module) => Ok(Module {
-                raw_module: module,
-                features: options.features.clone(),
-                lexer: None,
-            }),
+            Ok(module) => {
+                // Create the module first, so it is destroyed if reading the sections fails.
+                let mut module = Module {
+                    raw_module: module,
+                    features,
+                    lexer: None,
+                    custom_sections: Vec::new(),
+                    warnings: errors.diagnostics(),
+                };
+                if options.read_custom_sections {
+                    module.custom_sections = read_sections(wasm)?
+                        .into_iter()
+                        .filter(|(section, _)| section.id == 0)
+                        .map(|(section, contents)| (section.name, wasm[contents].to_vec()))
+                        .collect();
+                }
+                Ok(module)
+            }
             Err(()) => {
                 let msg = String::from_utf8_lossy(errors.format_binary().as_ref()).to_string();
-                Err(Error(ErrorKind::Deserialize(msg)))
+                Err(Error::with_diagnostics(
+                    ErrorKind::Deserialize(msg),
+                    errors.diagnostics(),
+                ))
             }
         }
     }
 
-    fn resolve_names(&mut self) -> Result<(), Error> {
-        let errors = Errors::new();
+    /// Read a WebAssembly binary that may be followed by other data.
+    ///
+    /// wabt reads sections up to the first one it can't read, and the module is read from
+    /// the bytes up to the end of the last section that it could read. Returns the module
+    /// along with the number of bytes consumed, so a count smaller than `wasm.len()` means
+    /// there are trailing bytes, such as another module.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// extern crate wabt;
+    /// use wabt::{wat2wasm, Module, ReadBinaryOptions};
+    ///
+    /// fn main() {
+    ///     let mut wasm = wat2wasm("(module (func))").unwrap();
+    ///     let len = wasm.len();
+    ///     wasm.extend_from_slice(b"trailing garbage");
+    ///
+    ///     let (_module, consumed) =
+    ///         Module::read_binary_prefix(&wasm, &ReadBinaryOptions::default()).unwrap();
+    ///     assert_eq!(consumed, len);
+    /// }
+    /// ```
+    pub fn read_binary_prefix<S: AsRef<[u8]>>(
+        wasm: S,
+        options: &ReadBinaryOptions,
+    ) -> Result<(Module, usize), Error> {
+        let wasm = wasm.as_ref();
+        // wabt's reader treats any trailing bytes as a malformed section, so find the end
+        // of the sections first and only hand wabt those.
+        let consumed = unsafe {
+            ffi::wabt_read_binary_prefix_len(wasm.as_ptr(), wasm.len(), options.features.raw)
+        };
+        let module = Module::read_binary(&wasm[..consumed], options)?;
+        Ok((module, consumed))
+    }
+
+    /// Resolve symbolic names (such as `$func`) into indices.
+    ///
+    /// Modules parsed from text refer to items by name until their names are resolved, and
+    /// both [`validate`] and [`write_binary`] expect resolved references. Modules read from a
+    /// binary only refer to items by index, so resolving is a no-op for them unless names
+    /// were applied with [`apply_names`].
+    ///
+    /// [`validate`]: #method.validate
+    /// [`write_binary`]: #method.write_binary
+    /// [`apply_names`]: #method.apply_names
+    pub fn resolve_names(&mut self) -> Result<(), Error> {
+        self.resolve_names_with_errors(&Errors::new())
+    }
+
+    fn resolve_names_with_errors(&mut self, errors: &Errors) -> Result<(), Error> {
+        errors.clear();
         unsafe {
             let result = ffi::wabt_resolve_names_module(self.raw_module, errors.raw);
             if result == ffi::Result::Error {
@@ -801,15 +1733,361 @@ impl Module {
                     errors.format_binary()
                 };
                 let msg = String::from_utf8_lossy(buf.as_ref()).to_string();
-                return Err(Error(ErrorKind::ResolveNames(msg)));
+                return Err(Error::with_diagnostics(
+                    ErrorKind::ResolveNames(msg),
+                    errors.diagnostics(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Generate names for all unnamed items in the module.
+    ///
+    /// This is useful for binaries without debug names, since the text output will then
+    /// use synthetic identifiers (such as `$f0`) instead of bare indices.
+    pub fn generate_names(&mut self) -> Result<(), Error> {
+        unsafe {
+            let result = ffi::wabt_generate_names_module(self.raw_module);
+            if result == ffi::Result::Error {
+                // wabt doesn't report diagnostics for name generation.
+                return Err(Error::from(ErrorKind::GenerateNames(
+                    "the module refers to an item that doesn't exist".to_owned(),
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply names to the module, turning index references back into named references.
+    ///
+    /// This must be called after names were resolved, since it relies on the indices
+    /// produced by the name resolution. Use it together with [`generate_names`] to get text
+    /// output where every reference uses an identifier.
+    ///
+    /// [`generate_names`]: #method.generate_names
+    pub fn apply_names(&mut self) -> Result<(), Error> {
+        unsafe {
+            let result = ffi::wabt_apply_names_module(self.raw_module);
+            if result == ffi::Result::Error {
+                // wabt doesn't report diagnostics for applying names, it only fails when
+                // a reference can't be looked up.
+                return Err(Error::from(ErrorKind::ApplyNames(
+                    "the module refers to an item that doesn't exist".to_owned(),
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Disassemble a single function to wasm text format.
+    ///
+    /// `index` is an index in the function index space, so imported functions come first.
+    /// Since imported functions don't have a body, requesting one of them is an error.
+    ///
+    /// This writes the text of the whole module to pick the function from it, so to
+    /// disassemble many functions use [`function_texts`], which writes the module only once.
+    ///
+    /// [`function_texts`]: #method.function_texts
+    pub fn function_text(&self, index: u32) -> Result<String, Error> {
+        let text = self.write_function_fields()?;
+        match split_function_fields(&text).into_iter().nth(index as usize) {
+            Some(Some(func)) => Ok(func.to_owned()),
+            _ => Err(Error::from(ErrorKind::InvalidFunctionIndex(index))),
+        }
+    }
+
+    /// Disassemble every function defined in the module to wasm text format.
+    ///
+    /// Returns the text of each function along with its index in the function index space,
+    /// in order. Imported functions don't have a body, so they are skipped.
+    pub fn function_texts(&self) -> Result<Vec<(u32, String)>, Error> {
+        let text = self.write_function_fields()?;
+        Ok(split_function_fields(&text)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, func)| func.map(|func| (index as u32, func.to_owned())))
+            .collect())
+    }
+
+    fn write_function_fields(&self) -> Result<String, Error> {
+        let text = self.write_text(&WriteTextOptions::default())?;
+        String::from_utf8(text.as_ref().to_vec()).map_err(|_| Error::from(ErrorKind::NonUtf8Result))
+    }
+
+    /// Function types declared in the type section, in order.
+    ///
+    /// Types that aren't function types, such as the struct types of the GC proposal, are
+    /// skipped. Fails if a type uses a value type that [`ValueType`] can't represent.
+    ///
+    /// [`ValueType`]: enum.ValueType.html
+    pub fn types(&self) -> Result<Vec<FuncType>, Error> {
+        let count = unsafe { ffi::wabt_module_get_type_count(self.raw_module) };
+        let mut types = Vec::with_capacity(count);
+        for index in 0..count {
+            unsafe {
+                let sig = ffi::wabt_module_get_func_type_signature(self.raw_module, index);
+                if !sig.is_null() {
+                    types.push(FuncType::from_raw(sig)?);
+                }
             }
         }
+        Ok(types)
+    }
+
+    /// Imports of the module, in order.
+    ///
+    /// Fails if an import uses a value type that [`ValueType`] can't represent.
+    ///
+    /// [`ValueType`]: enum.ValueType.html
+    pub fn imports(&self) -> Result<Vec<Import>, Error> {
+        let raw_module = self.raw_module;
+        let count = unsafe { ffi::wabt_module_get_import_count(raw_module) };
+        let mut imports = Vec::with_capacity(count);
+        for index in 0..count {
+            let import = unsafe {
+                let module =
+                    CStr::from_ptr(ffi::wabt_module_get_import_module_name(raw_module, index));
+                let field =
+                    CStr::from_ptr(ffi::wabt_module_get_import_field_name(raw_module, index));
+                let kind = match ffi::wabt_module_get_import_kind(raw_module, index) {
+                    0 => ImportKind::Func(FuncType::from_raw(
+                        ffi::wabt_module_get_import_func_signature(raw_module, index),
+                    )?),
+                    1 => ImportKind::Table {
+                        elem_type: ValueType::from_raw(
+                            ffi::wabt_module_get_import_table_elem_type(raw_module, index),
+                        )?,
+                        limits: Limits::from_raw(ffi::wabt_module_get_import_limits(
+                            raw_module, index,
+                        )),
+                    },
+                    2 => ImportKind::Memory {
+                        limits: Limits::from_raw(ffi::wabt_module_get_import_limits(
+                            raw_module, index,
+                        )),
+                    },
+                    3 => ImportKind::Global {
+                        value_type: ValueType::from_raw(ffi::wabt_module_get_import_global_type(
+                            raw_module, index,
+                        ))?,
+                        mutable: ffi::wabt_module_get_import_global_mutable(raw_module, index) != 0,
+                    },
+                    _ => ImportKind::Tag,
+                };
+                Import {
+                    module: module.to_string_lossy().into_owned(),
+                    field: field.to_string_lossy().into_owned(),
+                    kind,
+                }
+            };
+            imports.push(import);
+        }
+        Ok(imports)
+    }
+
+    /// Number of functions defined in the module, i.e. excluding imported functions.
+    pub fn func_count(&self) -> usize {
+        self.defined_item_count(0)
+    }
+
+    /// Number of tables defined in the module, i.e. excluding imported tables.
+    pub fn table_count(&self) -> usize {
+        self.defined_item_count(1)
+    }
+
+    /// Number of memories defined in the module, i.e. excluding imported memories.
+    pub fn memory_count(&self) -> usize {
+        self.defined_item_count(2)
+    }
+
+    /// Number of globals defined in the module, i.e. excluding imported globals.
+    pub fn global_count(&self) -> usize {
+        self.defined_item_count(3)
+    }
+
+    /// Count the items of the given wabt `ExternalKind` that aren't imported.
+    fn defined_item_count(&self, kind: c_int) -> usize {
+        unsafe {
+            ffi::wabt_module_get_item_count(self.raw_module, kind)
+                - ffi::wabt_module_get_imported_item_count(self.raw_module, kind)
+        }
+    }
+
+    /// Rename the export `old` to `new`.
+    ///
+    /// Fails if there is no export named `old`, or if there already is one named `new`.
+    pub fn rename_export(&mut self, old: &str, new: &str) -> Result<(), Error> {
+        let old_name = CString::new(old)?;
+        let new_name = CString::new(new)?;
+        let result = unsafe {
+            ffi::wabt_module_rename_export(self.raw_module, old_name.as_ptr(), new_name.as_ptr())
+        };
+        match result {
+            0 => Ok(()),
+            1 => Err(Error::from(ErrorKind::UnknownExport(old.to_owned()))),
+            _ => Err(Error::from(ErrorKind::DuplicateExport(new.to_owned()))),
+        }
+    }
+
+    /// Add a custom section, which is written after the other sections of the binary.
+    ///
+    /// Custom sections with the same name are allowed, so this never replaces an existing
+    /// section.
+    pub fn add_custom_section(&mut self, name: &str, data: &[u8]) -> Result<(), Error> {
+        let name = CString::new(name)?;
+        unsafe {
+            ffi::wabt_module_add_custom_section(
+                self.raw_module,
+                name.as_ptr(),
+                data.as_ptr(),
+                data.len(),
+            );
+        }
         Ok(())
     }
 
+    fn clamp_memory_pages(&mut self, max_pages: u32) {
+        unsafe { ffi::wabt_module_clamp_memory_pages(self.raw_module, u64::from(max_pages)) }
+    }
+
+    /// Data segments of the module, in order.
+    pub fn data_segments(&self) -> Vec<DataSegment> {
+        let raw_module = self.raw_module;
+        let count = unsafe { ffi::wabt_module_get_data_segment_count(raw_module) };
+        (0..count)
+            .map(|index| unsafe {
+                let mode = match ffi::wabt_module_get_data_segment_kind(raw_module, index) {
+                    0 => {
+                        let value =
+                            || ffi::wabt_module_get_data_segment_offset_value(raw_module, index);
+                        let offset = match ffi::wabt_module_get_data_segment_offset_kind(
+                            raw_module, index,
+                        ) {
+                            0 => OffsetExpr::I32Const(value() as u32 as i32),
+                            1 => OffsetExpr::I64Const(value() as i64),
+                            2 => OffsetExpr::GlobalGet(value() as u32),
+                            _ => OffsetExpr::Other,
+                        };
+                        DataSegmentMode::Active {
+                            memory: ffi::wabt_module_get_data_segment_memory_index(
+                                raw_module, index,
+                            ),
+                            offset,
+                        }
+                    }
+                    _ => DataSegmentMode::Passive,
+                };
+                // The data pointer may be null for empty segments, which `from_raw_parts`
+                // doesn't allow.
+                let size = ffi::wabt_module_get_data_segment_size(raw_module, index);
+                let data = if size == 0 {
+                    Vec::new()
+                } else {
+                    slice::from_raw_parts(
+                        ffi::wabt_module_get_data_segment_data(raw_module, index),
+                        size,
+                    )
+                    .to_vec()
+                };
+                DataSegment { mode, data }
+            })
+            .collect()
+    }
+
+    /// Exports of the module, in order.
+    ///
+    /// Works on modules that haven't been validated, but fails if an export refers to an
+    /// item that doesn't exist or if a function signature uses a value type that
+    /// [`ValueType`] can't represent.
+    ///
+    /// [`ValueType`]: enum.ValueType.html
+    pub fn exports(&self) -> Result<Vec<ModuleExport>, Error> {
+        let raw_module = self.raw_module;
+        let count = unsafe { ffi::wabt_module_get_export_count(raw_module) };
+        let mut exports = Vec::with_capacity(count);
+        for index in 0..count {
+            let export = unsafe {
+                let name = CStr::from_ptr(ffi::wabt_module_get_export_name(raw_module, index))
+                    .to_string_lossy()
+                    .into_owned();
+                let item_index = ffi::wabt_module_get_export_item_index(raw_module, index);
+                if item_index == u32::MAX {
+                    return Err(Error::from(ErrorKind::UnknownExportItem(name)));
+                }
+                let kind = match ffi::wabt_module_get_export_kind(raw_module, index) {
+                    0 => ExportKind::Func(FuncType::from_raw(
+                        ffi::wabt_module_get_export_func_signature(raw_module, index),
+                    )?),
+                    1 => ExportKind::Table,
+                    2 => ExportKind::Memory,
+                    3 => ExportKind::Global,
+                    _ => ExportKind::Tag,
+                };
+                ModuleExport {
+                    name,
+                    kind,
+                    index: item_index,
+                }
+            };
+            exports.push(export);
+        }
+        Ok(exports)
+    }
+
+    /// Custom sections of the binary the module was read from, as `(name, contents)` pairs.
+    ///
+    /// The sections are only kept if the module was read with
+    /// [`ReadBinaryOptions::read_custom_sections`] enabled, otherwise and for modules parsed
+    /// from text this is empty. The name section is included as well, even though it is
+    /// also read into the module with debug names.
+    ///
+    /// [`ReadBinaryOptions::read_custom_sections`]:
+    /// struct.ReadBinaryOptions.html#method.read_custom_sections
+    pub fn custom_sections(&self) -> &[(String, Vec<u8>)] {
+        &self.custom_sections
+    }
+
+    /// Diagnostics that wabt reported while parsing or reading the module, even though it
+    /// succeeded.
+    ///
+    /// These are warnings, such as the errors in malformed custom sections that are skipped
+    /// with [`ReadBinaryOptions::fail_on_custom_section_error`] disabled.
+    ///
+    /// [`ReadBinaryOptions::fail_on_custom_section_error`]:
+    /// struct.ReadBinaryOptions.html#method.fail_on_custom_section_error
+    pub fn warnings(&self) -> &[WabtDiagnostic] {
+        &self.warnings
+    }
+
+    /// Symbols of the linking section the module gets when written as a relocatable binary.
+    ///
+    /// These are the symbols of the binary produced by [`Wat2Wasm::relocatable`]. wabt
+    /// doesn't keep the linking section of a binary the module was read from, so for a module
+    /// read from a relocatable binary the symbols are generated anew and their flags may
+    /// differ from the original ones. Use [`linking_symbols`] to read them from the binary
+    /// instead.
+    ///
+    /// [`Wat2Wasm::relocatable`]: struct.Wat2Wasm.html#method.relocatable
+    /// [`linking_symbols`]: fn.linking_symbols.html
+    pub fn linking_symbols(&self) -> Result<Vec<Symbol>, Error> {
+        let mut write_options = WriteBinaryOptions::default();
+        write_options.relocatable(true);
+        let binary = self.write_binary(&write_options)?;
+        let read_options = ReadBinaryOptions {
+            features: (*self.features).clone(),
+            ..ReadBinaryOptions::default()
+        };
+        linking_symbols(&binary, &read_options)
+    }
+
     /// Validate the module.
     pub fn validate(&self) -> Result<(), Error> {
-        let errors = Errors::new();
+        self.validate_with_errors(&Errors::new())
+    }
+
+    fn validate_with_errors(&self, errors: &Errors) -> Result<(), Error> {
+        errors.clear();
         unsafe {
             let result = ffi::wabt_validate_module(self.raw_module, self.features.raw, errors.raw);
             if result == ffi::Result::Error {
@@ -819,29 +2097,100 @@ impl Module {
                     errors.format_binary()
                 };
                 let msg = String::from_utf8_lossy(buf.as_ref()).to_string();
-                return Err(Error(ErrorKind::Validate(msg)));
+                return Err(Error::with_diagnostics(
+                    ErrorKind::Validate(msg),
+                    errors.diagnostics(),
+                ));
             }
         }
         Ok(())
     }
 
-    fn write_binary(&self, options: &WriteBinaryOptions) -> Result<WabtBuf, Error> {
-        let result = unsafe {
-            let raw_result = ffi::wabt_write_binary_module(
-                self.raw_module,
-                options.log as c_int,
-                options.canonicalize_lebs as c_int,
-                options.relocatable as c_int,
-                options.write_debug_names as c_int,
-            );
-            WriteModuleResult { raw_result }
-        };
-        result
+    /// Write the module as a wasm binary.
+    ///
+    /// The module isn't validated first, call [`validate`] if needed. Names must be resolved
+    /// though, which is the case for modules read from a binary, and for modules parsed from
+    /// text after [`resolve_names`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// extern crate wabt;
+    /// use wabt::{wat2wasm, Module, ReadBinaryOptions, WriteBinaryOptions};
+    ///
+    /// fn main() {
+    ///     let wasm = wat2wasm("(module (func (export \"f\")))").unwrap();
+    ///     let mut module = Module::read_binary(&wasm, &ReadBinaryOptions::default()).unwrap();
+    ///     module.rename_export("f", "g").unwrap();
+    ///     let renamed = module.write_binary(&WriteBinaryOptions::default()).unwrap();
+    ///     assert_eq!(renamed.as_ref().len(), wasm.len());
+    /// }
+    /// ```
+    ///
+    /// [`validate`]: #method.validate
+    /// [`resolve_names`]: #method.resolve_names
+    pub fn write_binary(&self, options: &WriteBinaryOptions) -> Result<WabtBuf, Error> {
+        self.write_binary_result(options)
             .take_wabt_buf()
-            .map_err(|_| Error(ErrorKind::WriteBinary))
+            .map_err(|_| Error::from(ErrorKind::WriteBinary))
+    }
+
+    fn write_binary_with_log(
+        &self,
+        options: &WriteBinaryOptions,
+    ) -> Result<(WabtBuf, String), Error> {
+        let result = self.write_binary_result(options);
+        if !options.log {
+            let binary = result
+                .take_wabt_buf()
+                .map_err(|_| Error::from(ErrorKind::WriteBinary))?;
+            return Ok((binary, String::new()));
+        }
+        let (binary, log) = result
+            .take_wabt_buf_and_log()
+            .map_err(|_| Error::from(ErrorKind::WriteBinary))?;
+        Ok((binary, String::from_utf8_lossy(log.as_ref()).into_owned()))
+    }
+
+    fn write_binary_result(&self, options: &WriteBinaryOptions) -> WriteModuleResult {
+        unsafe {
+            let raw_result = match options.name_section {
+                NameSectionLevel::FunctionsOnly => {
+                    ffi::wabt_write_binary_module_without_local_names(
+                        self.raw_module,
+                        options.log as c_int,
+                        options.canonicalize_lebs as c_int,
+                        options.relocatable as c_int,
+                    )
+                }
+                level => ffi::wabt_write_binary_module(
+                    self.raw_module,
+                    options.log as c_int,
+                    options.canonicalize_lebs as c_int,
+                    options.relocatable as c_int,
+                    (level == NameSectionLevel::Full) as c_int,
+                ),
+            };
+            WriteModuleResult { raw_result }
+        }
     }
 
-    fn write_text(&self, options: &WriteTextOptions) -> Result<WabtBuf, Error> {
+    /// Write the module in the wasm text format.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// extern crate wabt;
+    /// use wabt::{wat2wasm, Module, ReadBinaryOptions, WriteTextOptions};
+    ///
+    /// fn main() {
+    ///     let wasm = wat2wasm("(module)").unwrap();
+    ///     let module = Module::read_binary(&wasm, &ReadBinaryOptions::default()).unwrap();
+    ///     let text = module.write_text(&WriteTextOptions::default()).unwrap();
+    ///     assert_eq!(text.as_ref(), b"(module)\n");
+    /// }
+    /// ```
+    pub fn write_text(&self, options: &WriteTextOptions) -> Result<WabtBuf, Error> {
         let result = unsafe {
             let raw_result = ffi::wabt_write_text_module(
                 self.raw_module,
@@ -850,9 +2199,14 @@ impl Module {
             );
             WriteModuleResult { raw_result }
         };
-        result
+        let text = result
             .take_wabt_buf()
-            .map_err(|_| Error(ErrorKind::WriteText))
+            .map_err(|_| Error::from(ErrorKind::WriteText))?;
+        if !options.explicit_indices {
+            return Ok(text);
+        }
+        let text = str::from_utf8(&text).map_err(|_| Error::from(ErrorKind::NonUtf8Result))?;
+        Ok(WabtBuf::from_slice(write_index_comments(text).as_bytes()))
     }
 }
 
@@ -864,106 +2218,660 @@ impl Drop for Module {
     }
 }
 
-/// A builder for translating wasm text source to wasm binary format.
-///
-/// This version allows you to tweak parameters. If you need simple version
-/// check out [`wat2wasm`].
-///
-/// [`wat2wasm`]: fn.wat2wasm.html
-///
-/// # Examples
+/// Parse a module in the wasm text format with default features.
 ///
-/// ```rust
-/// extern crate wabt;
-/// use wabt::Wat2Wasm;
+/// This is the same as [`Module::parse_wat_str`] with `Features::new()`. Names aren't
+/// resolved and the module isn't validated.
 ///
-/// fn main() {
-///     let wasm_binary = Wat2Wasm::new()
-///         .canonicalize_lebs(false)
-///         .write_debug_names(true)
-///         .convert(
-///             r#"
-///                 (module
-///                     (import "spectest" "print" (func $print (param i32)))
-///                     (func (export "main")
-///                         i32.const 1312
-///                         call $print
-///                     )
-///                 )
-///             "#
-///         ).unwrap();
+/// [`Module::parse_wat_str`]: struct.Module.html#method.parse_wat_str
+impl TryFrom<&str> for Module {
+    type Error = Error;
+
+    fn try_from(source: &str) -> Result<Module, Error> {
+        Module::parse_wat_str(source, Features::new())
+    }
+}
+
+/// Read a wasm binary with default options.
 ///
-///     # wasm_binary;
-/// }
-/// ```
+/// This is the same as [`Module::read_binary`] with `ReadBinaryOptions::default()`. Bytes
+/// are always read as a binary, even if they hold text; use [`Module::parse_wat`] for
+/// text sources given as bytes.
 ///
-pub struct Wat2Wasm {
-    validate: bool,
-    write_binary_options: WriteBinaryOptions,
-    features: Features,
+/// [`Module::read_binary`]: struct.Module.html#method.read_binary
+/// [`Module::parse_wat`]: struct.Module.html#method.parse_wat
+impl TryFrom<&[u8]> for Module {
+    type Error = Error;
+
+    fn try_from(wasm: &[u8]) -> Result<Module, Error> {
+        Module::read_binary(wasm, &ReadBinaryOptions::default())
+    }
 }
 
-impl Wat2Wasm {
-    /// Create `Wat2Wasm` with default configuration.
-    pub fn new() -> Wat2Wasm {
-        Wat2Wasm {
-            write_binary_options: WriteBinaryOptions::default(),
-            validate: true,
-            features: Features::new(),
+// The module and the lexer are owned exclusively by `Module` and wabt doesn't keep any
+// thread-local or global state for them.
+unsafe impl Send for Module {}
+
+/// Split wasm text into its top-level s-expressions, skipping strings and comments.
+fn sexpr_children(text: &str) -> Vec<&str> {
+    let bytes = text.as_bytes();
+    let mut children = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            b';' if bytes.get(i + 1) == Some(&b';') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'(' if bytes.get(i + 1) == Some(&b';') => {
+                let mut comment_depth = 1;
+                i += 2;
+                while i < bytes.len() && comment_depth > 0 {
+                    if bytes[i..].starts_with(b"(;") {
+                        comment_depth += 1;
+                        i += 1;
+                    } else if bytes[i..].starts_with(b";)") {
+                        comment_depth -= 1;
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                continue;
+            }
+            b'(' => {
+                if depth == 0 {
+                    start = i;
+                }
+                depth += 1;
+            }
+            b')' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    children.push(&text[start..=i]);
+                }
+            }
+            _ => {}
         }
+        i += 1;
     }
+    children
+}
 
-    /// Write canonicalized LEB128 for var ints.
-    ///
-    /// Set this to `false` to write all LEB128 sizes as 5-bytes instead of their minimal size.
-    /// `true` by default.
-    pub fn canonicalize_lebs(&mut self, canonicalize_lebs: bool) -> &mut Wat2Wasm {
-        self.write_binary_options.canonicalize_lebs = canonicalize_lebs;
-        self
-    }
+/// Split the text of a module into the fields of its functions, in function index order.
+///
+/// Imported functions are `None`. The fields are found by their s-expression structure, so
+/// this doesn't depend on how the writer lays out the text.
+fn split_function_fields(text: &str) -> Vec<Option<&str>> {
+    let module = sexpr_children(text).into_iter().next().unwrap_or("()");
+    sexpr_children(&module[1..module.len() - 1])
+        .into_iter()
+        .filter_map(|field| {
+            if field.starts_with("(func") {
+                Some(Some(field))
+            } else if field.starts_with("(import") {
+                let desc = sexpr_children(&field[1..field.len() - 1]);
+                match desc.first() {
+                    Some(desc) if desc.starts_with("(func") => Some(None),
+                    _ => None,
+                }
+            } else {
+                None
+            }
+        })
+        .collect()
+}
 
-    /// Create a relocatable wasm binary
-    ///
-    /// (suitable for linking with wasm-link).
-    /// `false` by default.
-    pub fn relocatable(&mut self, relocatable: bool) -> &mut Wat2Wasm {
-        self.write_binary_options.relocatable = relocatable;
-        self
-    }
+/// Add an index comment, such as `(;1;)`, to every named module item.
+///
+/// wabt only writes the index comments in place of a missing name, and its text writer has
+/// no option to write them for named items too, so they are added to the text afterwards.
+fn write_index_comments(text: &str) -> String {
+    // Every index space of the text format. Fields of any other kind, such as `export` and
+    // `start`, don't define an item.
+    const KINDS: &[&str] = &[
+        "type", "func", "table", "memory", "global", "tag", "elem", "data",
+    ];
 
-    /// Write debug names to the generated binary file
-    ///
-    /// `false` by default.
-    pub fn write_debug_names(&mut self, write_debug_names: bool) -> &mut Wat2Wasm {
-        self.write_binary_options.write_debug_names = write_debug_names;
-        self
+    fn keyword(sexpr: &str) -> &str {
+        sexpr[1..]
+            .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+            .next()
+            .unwrap_or("")
     }
 
-    /// Check for validity of module before writing.
-    ///
-    /// `true` by default.
-    pub fn validate(&mut self, validate: bool) -> &mut Wat2Wasm {
-        self.validate = validate;
-        self
+    let offset_of = |slice: &str| slice.as_ptr() as usize - text.as_ptr() as usize;
+    let mut counts = vec![0; KINDS.len()];
+    let mut insertions = Vec::new();
+
+    let module = match sexpr_children(text).into_iter().next() {
+        Some(module) => module,
+        None => return text.to_owned(),
+    };
+    for field in sexpr_children(&module[1..module.len() - 1]) {
+        let item = if keyword(field) == "import" {
+            match sexpr_children(&field[1..field.len() - 1])
+                .into_iter()
+                .next()
+            {
+                Some(desc) => desc,
+                None => continue,
+            }
+        } else {
+            field
+        };
+        let kind = match KINDS.iter().position(|&kind| kind == keyword(item)) {
+            Some(kind) => kind,
+            None => continue,
+        };
+        let index = counts[kind];
+        counts[kind] += 1;
+
+        let rest = &item[1 + keyword(item).len()..];
+        let name = rest.trim_start();
+        if name.starts_with('$') {
+            let name_len = name
+                .find(|c: char| c.is_whitespace() || c == '(' || c == ')')
+                .unwrap_or(name.len());
+            insertions.push((offset_of(name) + name_len, index));
+        }
     }
 
-    // TODO: Add logged version of convert
+    let mut result = String::with_capacity(text.len() + insertions.len() * 8);
+    let mut last = 0;
+    for (offset, index) in insertions {
+        result.push_str(&text[last..offset]);
+        result.push_str(&format!(" (;{};)", index));
+        last = offset;
+    }
+    result.push_str(&text[last..]);
+    result
+}
 
-    /// Perform conversion.
-    pub fn convert<S: AsRef<[u8]>>(&self, source: S) -> Result<WabtBuf, Error> {
-        let mut module = Module::parse_wat("test.wast", source, self.features.clone())?;
-        module.resolve_names()?;
+struct InstructionOffsets {
+    raw_result: *mut ffi::WabtInstructionOffsets,
+}
 
-        if self.validate {
-            module.validate()?;
+impl InstructionOffsets {
+    fn read(wasm: &[u8], features: &Features) -> Result<InstructionOffsets, Error> {
+        let errors = Errors::new();
+        let raw_result = unsafe {
+            ffi::wabt_read_instruction_offsets(wasm.as_ptr(), wasm.len(), features.raw, errors.raw)
+        };
+        let offsets = InstructionOffsets { raw_result };
+        if unsafe { ffi::wabt_instruction_offsets_get_result(raw_result) } != ffi::Result::Ok {
+            let msg = String::from_utf8_lossy(errors.format_binary().as_ref()).to_string();
+            return Err(Error::with_diagnostics(
+                ErrorKind::Deserialize(msg),
+                errors.diagnostics(),
+            ));
         }
+        Ok(offsets)
+    }
 
-        let result = module.write_binary(&self.write_binary_options)?;
-        Ok(result)
+    /// Offsets and names of the instructions of a function body, by the index of the body.
+    fn func(&self, func: usize) -> Vec<(usize, &str)> {
+        unsafe {
+            if func >= ffi::wabt_instruction_offsets_get_func_count(self.raw_result) {
+                return Vec::new();
+            }
+            let count = ffi::wabt_instruction_offsets_get_count(self.raw_result, func);
+            (0..count)
+                .map(|index| {
+                    let offset =
+                        ffi::wabt_instruction_offsets_get_offset(self.raw_result, func, index);
+                    let name =
+                        ffi::wabt_instruction_offsets_get_opcode_name(self.raw_result, func, index);
+                    (offset, CStr::from_ptr(name).to_str().unwrap_or(""))
+                })
+                .collect()
+        }
     }
 }
 
-/// A builder for converting wasm binary to wasm text format.
+impl Drop for InstructionOffsets {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::wabt_destroy_instruction_offsets(self.raw_result);
+        }
+    }
+}
+
+/// Prefix every instruction in unfolded text output with a comment holding its offset in
+/// the binary, such as `(;@0x1a;)`.
+///
+/// The instructions of each function are matched with the lines of its body, and every
+/// line has to start with the name of its instruction. If the text doesn't line up with the
+/// binary this way, this fails instead of writing wrong offsets.
+fn write_offset_comments(text: &str, offsets: &InstructionOffsets) -> Result<String, Error> {
+    fn check_consumed(
+        func: Option<usize>,
+        body: &mut vec::IntoIter<(usize, &str)>,
+    ) -> Result<(), Error> {
+        match (func, body.next()) {
+            (Some(func), Some((offset, name))) => {
+                Err(Error::from(ErrorKind::WriteOffsets(format!(
+                    "instruction `{}` at {:#x} of function body {} has no line in the text",
+                    name, offset, func
+                ))))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    let mut result = String::with_capacity(text.len() * 2);
+    let mut bodies = 0;
+    let mut func = None;
+    let mut body = Vec::new().into_iter();
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        if indent <= 2 {
+            check_consumed(func, &mut body)?;
+            if trimmed.starts_with("(func") {
+                body = offsets.func(bodies).into_iter();
+                func = Some(bodies);
+                bodies += 1;
+            } else {
+                body = Vec::new().into_iter();
+                func = None;
+            }
+        } else if let Some(func) = func {
+            if !trimmed.starts_with("(local") {
+                let keyword = trimmed
+                    .split(|c: char| c.is_whitespace() || c == ')')
+                    .next()
+                    .unwrap_or("");
+                let (offset, name) = body.next().ok_or_else(|| {
+                    Error::from(ErrorKind::WriteOffsets(format!(
+                        "line `{}` of function body {} has no instruction in the binary",
+                        trimmed, func
+                    )))
+                })?;
+                if keyword != name {
+                    return Err(Error::from(ErrorKind::WriteOffsets(format!(
+                        "line `{}` of function body {} doesn't match instruction `{}` at {:#x}",
+                        trimmed, func, name, offset
+                    ))));
+                }
+                result.push_str(&line[..indent]);
+                result.push_str(&format!("(;@{:#x};) ", offset));
+                result.push_str(trimmed);
+                result.push('\n');
+                continue;
+            }
+        }
+        result.push_str(line);
+        result.push('\n');
+    }
+    check_consumed(func, &mut body)?;
+    Ok(result)
+}
+
+/// Context for parsing and converting many modules with the same features.
+///
+/// Functions such as [`Module::parse_wat`] and [`wat2wasm_with_features`] create the wabt
+/// objects they need, such as the features and the error lists, for every call. A context
+/// creates them once and reuses them for every module, which adds up for tools converting
+/// thousands of small modules:
+///
+/// - The modules parsed with a context share its features instead of getting a copy.
+/// - Parsing, resolving names, validating and reading binaries all report their
+///   diagnostics to the error list of the context.
+/// - [`wat2wasm`] parses the source in place, since the module doesn't outlive the call.
+///
+/// This is the type proposed as `WabtContext`. It was folded into `ParseContext`, so that
+/// there is a single context type rather than one for the wabt objects and one for parsing.
+///
+/// Run `cargo bench --bench context` to compare the context with the free functions. It
+/// converts 10,000 small modules each way and prints both timings and the speedup. The
+/// saving depends on the machine and on the wabt version `wabt-sys` is built against.
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate wabt;
+/// use wabt::{Features, ParseContext};
+///
+/// fn main() {
+///     let context = ParseContext::new(Features::new());
+///     for source in &["(module)", "(module (func))"] {
+///         let module = context.parse_wat("test.wat", source).unwrap();
+///         module.validate().unwrap();
+///
+///         let binary = context.wat2wasm(source).unwrap();
+///         let text = context.wasm2wat(&binary).unwrap();
+///         assert!(text.starts_with("(module"));
+///     }
+/// }
+/// ```
+///
+/// [`Module::parse_wat`]: struct.Module.html#method.parse_wat
+/// [`wat2wasm_with_features`]: fn.wat2wasm_with_features.html
+/// [`wat2wasm`]: #method.wat2wasm
+pub struct ParseContext {
+    features: Arc<Features>,
+    // Only the flags are used, the binaries are read with `features`.
+    read_binary_options: ReadBinaryOptions,
+    errors: Errors,
+}
+
+impl ParseContext {
+    /// Create `ParseContext` that parses with the given features.
+    pub fn new(features: Features) -> ParseContext {
+        ParseContext {
+            features: Arc::new(features),
+            read_binary_options: ReadBinaryOptions::default(),
+            errors: Errors::new(),
+        }
+    }
+
+    /// Features used for parsing.
+    pub fn features(&self) -> &Features {
+        &self.features
+    }
+
+    /// Parse source in WebAssembly text format.
+    ///
+    /// This is the same as [`Module::parse_wat`] with the features of this context. The
+    /// module keeps the source around, so it is copied. Use [`parse_wat_owned`] to avoid
+    /// the copy.
+    ///
+    /// [`Module::parse_wat`]: struct.Module.html#method.parse_wat
+    /// [`parse_wat_owned`]: #method.parse_wat_owned
+    pub fn parse_wat<S: AsRef<[u8]>>(&self, filename: &str, source: S) -> Result<Module, Error> {
+        self.parse_wat_owned(filename, source.as_ref().to_owned())
+    }
+
+    /// Parse source in WebAssembly text format, taking ownership of the source.
+    ///
+    /// This is the same as [`Module::parse_wat_owned`] with the features of this context.
+    ///
+    /// [`Module::parse_wat_owned`]: struct.Module.html#method.parse_wat_owned
+    pub fn parse_wat_owned(&self, filename: &str, source: Vec<u8>) -> Result<Module, Error> {
+        Module::parse_wat_with_errors(filename, source, Arc::clone(&self.features), &self.errors)
+    }
+
+    /// Translate wasm text source to wasm binary format, like [`wat2wasm_with_features`].
+    ///
+    /// [`wat2wasm_with_features`]: fn.wat2wasm_with_features.html
+    pub fn wat2wasm<S: AsRef<[u8]>>(&self, source: S) -> Result<Vec<u8>, Error> {
+        let source = source.as_ref();
+        // The module is dropped before returning, so the lexer can use the source in place.
+        let lexer = unsafe { Lexer::borrowing("test.wast", source)? };
+        let mut module =
+            Module::parse_wat_with_lexer(lexer, Arc::clone(&self.features), &self.errors)?;
+        module.resolve_names_with_errors(&self.errors)?;
+        module.validate_with_errors(&self.errors)?;
+        let binary = module.write_binary(&WriteBinaryOptions::default())?;
+        Ok(binary.into_vec())
+    }
+
+    /// Disassemble wasm binary to wasm text format, like [`wasm2wat_with_features`].
+    ///
+    /// [`wasm2wat_with_features`]: fn.wasm2wat_with_features.html
+    pub fn wasm2wat<S: AsRef<[u8]>>(&self, wasm: S) -> Result<String, Error> {
+        let module = Module::read_binary_with_errors(
+            wasm.as_ref(),
+            &self.read_binary_options,
+            Arc::clone(&self.features),
+            &self.errors,
+        )?;
+        let text = module.write_text(&WriteTextOptions::default())?;
+        String::from_utf8(text.into_vec()).map_err(|_| Error::from(ErrorKind::NonUtf8Result))
+    }
+}
+
+/// A builder for translating wasm text source to wasm binary format.
+///
+/// This version allows you to tweak parameters. If you need simple version
+/// check out [`wat2wasm`].
+///
+/// [`wat2wasm`]: fn.wat2wasm.html
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate wabt;
+/// use wabt::{Features, Wat2Wasm};
+///
+/// fn main() {
+///     let mut features = Features::new();
+///     features.enable_tail_call();
+///
+///     let wasm_binary = Wat2Wasm::new()
+///         .features(features)
+///         .canonicalize_lebs(false)
+///         .write_debug_names(true)
+///         .convert(
+///             r#"
+///                 (module
+///                     (import "spectest" "print" (func $print (param i32)))
+///                     (func (export "main")
+///                         i32.const 1312
+///                         call $print
+///                     )
+///                 )
+///             "#
+///         ).unwrap();
+///
+///     # wasm_binary;
+/// }
+/// ```
+///
+pub struct Wat2Wasm {
+    resolve_names: bool,
+    validate: bool,
+    max_memory_pages: Option<u32>,
+    write_binary_options: WriteBinaryOptions,
+    features: Features,
+}
+
+impl Wat2Wasm {
+    /// Create `Wat2Wasm` with default configuration.
+    pub fn new() -> Wat2Wasm {
+        Wat2Wasm {
+            write_binary_options: WriteBinaryOptions::default(),
+            resolve_names: true,
+            validate: true,
+            max_memory_pages: None,
+            features: Features::new(),
+        }
+    }
+
+    /// Support for pre-standard features.
+    pub fn features(&mut self, features: Features) -> &mut Wat2Wasm {
+        self.features = features;
+        self
+    }
+
+    /// Mutable access to the features used for parsing and validation.
+    pub fn features_mut(&mut self) -> &mut Features {
+        &mut self.features
+    }
+
+    /// Write canonicalized LEB128 for var ints.
+    ///
+    /// Set this to `false` to write all LEB128 sizes as 5-bytes instead of their minimal size.
+    /// `true` by default.
+    pub fn canonicalize_lebs(&mut self, canonicalize_lebs: bool) -> &mut Wat2Wasm {
+        self.write_binary_options.canonicalize_lebs = canonicalize_lebs;
+        self
+    }
+
+    /// Create a relocatable wasm binary
+    ///
+    /// (suitable for linking with a linker such as `wasm-ld`, wabt itself doesn't have
+    /// one). The contents of the linking and relocation sections can be read back with
+    /// [`linking_symbols`] and [`relocations`].
+    /// `false` by default.
+    ///
+    /// [`linking_symbols`]: fn.linking_symbols.html
+    /// [`relocations`]: fn.relocations.html
+    pub fn relocatable(&mut self, relocatable: bool) -> &mut Wat2Wasm {
+        self.write_binary_options.relocatable = relocatable;
+        self
+    }
+
+    /// Write debug names to the generated binary file
+    ///
+    /// `false` by default.
+    pub fn write_debug_names(&mut self, write_debug_names: bool) -> &mut Wat2Wasm {
+        self.write_binary_options.name_section = if write_debug_names {
+            NameSectionLevel::Full
+        } else {
+            NameSectionLevel::None
+        };
+        self
+    }
+
+    /// Select which names are written to the generated binary file.
+    ///
+    /// This is a finer grained version of [`write_debug_names`].
+    /// `NameSectionLevel::None` by default.
+    ///
+    /// [`write_debug_names`]: #method.write_debug_names
+    pub fn name_section(&mut self, name_section: NameSectionLevel) -> &mut Wat2Wasm {
+        self.write_binary_options.name_section = name_section;
+        self
+    }
+
+    /// Replace all of the options for writing the binary at once.
+    ///
+    /// This overrides [`canonicalize_lebs`], [`relocatable`], [`name_section`] and [`log`].
+    ///
+    /// [`canonicalize_lebs`]: #method.canonicalize_lebs
+    /// [`relocatable`]: #method.relocatable
+    /// [`name_section`]: #method.name_section
+    /// [`log`]: #method.log
+    pub fn write_binary_options(&mut self, options: WriteBinaryOptions) -> &mut Wat2Wasm {
+        self.write_binary_options = options;
+        self
+    }
+
+    /// Resolve symbolic names (such as `$func`) into indices before validating and writing.
+    ///
+    /// Turning this off only makes sense for sources that refer to everything by index, since
+    /// both validation and the binary writer expect resolved references.
+    /// `true` by default.
+    pub fn resolve_names(&mut self, resolve_names: bool) -> &mut Wat2Wasm {
+        self.resolve_names = resolve_names;
+        self
+    }
+
+    /// Check for validity of module before writing.
+    ///
+    /// Validation happens after names are resolved (if enabled with [`resolve_names`]).
+    /// `true` by default.
+    ///
+    /// [`resolve_names`]: #method.resolve_names
+    pub fn validate(&mut self, validate: bool) -> &mut Wat2Wasm {
+        self.validate = validate;
+        self
+    }
+
+    /// Clamp the sizes of all memories, including imported ones, to at most this many pages.
+    ///
+    /// Declared maximums above the cap are lowered to it, and so are initial sizes so that
+    /// they don't exceed the new maximum. This happens right after parsing, before
+    /// validation, so a module declaring an invalid maximum becomes valid.
+    /// `None` by default.
+    pub fn max_memory_pages(&mut self, max_memory_pages: Option<u32>) -> &mut Wat2Wasm {
+        self.max_memory_pages = max_memory_pages;
+        self
+    }
+
+    /// Record a log of the binary writer.
+    ///
+    /// The log is a verbose trace of every value written to the binary, which is handy for
+    /// debugging the encoding. Use [`convert_with_log`] to get it.
+    /// `false` by default.
+    ///
+    /// [`convert_with_log`]: #method.convert_with_log
+    pub fn log(&mut self, log: bool) -> &mut Wat2Wasm {
+        self.write_binary_options.log = log;
+        self
+    }
+
+    /// Perform conversion.
+    pub fn convert<S: AsRef<[u8]>>(&self, source: S) -> Result<WabtBuf, Error> {
+        let module = self.parse(source)?;
+        let result = module.write_binary(&self.write_binary_options)?;
+        Ok(result)
+    }
+
+    /// Perform conversion into a caller-owned buffer.
+    ///
+    /// `out` is cleared and the binary is copied into it, so the same buffer can be reused
+    /// for many conversions without allocating each time. If the conversion fails, `out` is
+    /// left untouched.
+    pub fn convert_into<S: AsRef<[u8]>>(&self, source: S, out: &mut Vec<u8>) -> Result<(), Error> {
+        let result = self.convert(source)?;
+        out.clear();
+        out.extend_from_slice(result.as_ref());
+        Ok(())
+    }
+
+    /// Perform conversion of a source read from `reader`.
+    ///
+    /// wabt needs the whole source in memory, so this doesn't reduce the peak memory usage:
+    /// the source is read into a buffer that is kept until the conversion finishes, while the
+    /// binary is written. It does save the caller from collecting the source first, and
+    /// unlike [`convert`] it doesn't make another copy of it.
+    ///
+    /// [`convert`]: #method.convert
+    pub fn convert_reader<R: io::Read>(&self, mut reader: R) -> Result<WabtBuf, Error> {
+        let mut source = Vec::new();
+        reader.read_to_end(&mut source).map_err(Error::from)?;
+        let module = self.parse_owned(source)?;
+        module.write_binary(&self.write_binary_options)
+    }
+
+    /// Perform conversion and return the binary along with the log of the binary writer.
+    ///
+    /// The log is empty unless it was enabled with [`log`].
+    ///
+    /// [`log`]: #method.log
+    pub fn convert_with_log<S: AsRef<[u8]>>(&self, source: S) -> Result<(WabtBuf, String), Error> {
+        let module = self.parse(source)?;
+        module.write_binary_with_log(&self.write_binary_options)
+    }
+
+    fn parse<S: AsRef<[u8]>>(&self, source: S) -> Result<Module, Error> {
+        self.parse_owned(source.as_ref().to_vec())
+    }
+
+    fn parse_owned(&self, source: Vec<u8>) -> Result<Module, Error> {
+        let mut module = Module::parse_wat_owned("test.wast", source, self.features.clone())?;
+
+        if let Some(max_memory_pages) = self.max_memory_pages {
+            module.clamp_memory_pages(max_memory_pages);
+        }
+
+        if self.resolve_names {
+            module.resolve_names()?;
+        }
+
+        if self.validate {
+            module.validate()?;
+        }
+
+        Ok(module)
+    }
+}
+
+/// A builder for converting wasm binary to wasm text format.
 ///
 /// # Examples
 ///
@@ -988,6 +2896,8 @@ impl Wat2Wasm {
 ///
 pub struct Wasm2Wat {
     read_binary_options: ReadBinaryOptions,
+    generate_names: bool,
+    with_offsets: bool,
     write_text_options: WriteTextOptions,
 }
 
@@ -996,6 +2906,8 @@ impl Wasm2Wat {
     pub fn new() -> Wasm2Wat {
         Wasm2Wat {
             read_binary_options: ReadBinaryOptions::default(),
+            generate_names: false,
+            with_offsets: false,
             write_text_options: WriteTextOptions::default(),
         }
     }
@@ -1014,6 +2926,16 @@ impl Wasm2Wat {
         self
     }
 
+    /// Generate names for all unnamed items, such as `$f0` for functions.
+    ///
+    /// Useful for binaries without debug names, which are otherwise disassembled with
+    /// bare indices.
+    /// `false` by default.
+    pub fn generate_names(&mut self, generate_names: bool) -> &mut Wasm2Wat {
+        self.generate_names = generate_names;
+        self
+    }
+
     /// Write folded expressions where possible.
     ///
     /// Example of folded code (if `true`):
@@ -1076,11 +2998,66 @@ impl Wasm2Wat {
         self
     }
 
+    /// Write the index comment, such as `(;0;)`, for every item, even if it has a name.
+    ///
+    /// By default, the index comments are only written for items without a name. Having
+    /// them everywhere makes the output easier to diff and to match against the binary.
+    ///
+    /// `false` by default.
+    pub fn explicit_indices(&mut self, explicit_indices: bool) -> &mut Wasm2Wat {
+        self.write_text_options.explicit_indices = explicit_indices;
+        self
+    }
+
+    /// Replace all of the options for writing the text at once.
+    ///
+    /// This overrides [`fold_exprs`], [`inline_export`] and [`explicit_indices`].
+    ///
+    /// [`fold_exprs`]: #method.fold_exprs
+    /// [`inline_export`]: #method.inline_export
+    /// [`explicit_indices`]: #method.explicit_indices
+    pub fn write_text_options(&mut self, options: WriteTextOptions) -> &mut Wasm2Wat {
+        self.write_text_options = options;
+        self
+    }
+
+    /// Prefix every instruction with a comment holding its offset in the binary, such as
+    /// `(;@0x1a;)`.
+    ///
+    /// Folded expressions don't keep the order of instructions in the binary, so conversion
+    /// fails if this is enabled together with [`fold_exprs`].
+    ///
+    /// `false` by default.
+    ///
+    /// [`fold_exprs`]: #method.fold_exprs
+    pub fn with_offsets(&mut self, with_offsets: bool) -> &mut Wasm2Wat {
+        self.with_offsets = with_offsets;
+        self
+    }
+
     /// Perform conversion.
     pub fn convert<S: AsRef<[u8]>>(&self, wasm: S) -> Result<WabtBuf, Error> {
-        let module = Module::read_binary(wasm, &self.read_binary_options)?;
-        let output_buffer = module.write_text(&self.write_text_options)?;
-        Ok(output_buffer)
+        let wasm = wasm.as_ref();
+        let mut module = Module::read_binary(wasm, &self.read_binary_options)?;
+        if self.generate_names {
+            module.generate_names()?;
+            module.apply_names()?;
+        }
+        if !self.with_offsets {
+            return module.write_text(&self.write_text_options);
+        }
+
+        if self.write_text_options.fold_exprs {
+            return Err(Error::from(ErrorKind::WriteOffsets(
+                "offsets can't be written for folded expressions".to_owned(),
+            )));
+        }
+        let text = module.write_text(&self.write_text_options)?;
+        let text = str::from_utf8(&text).map_err(|_| Error::from(ErrorKind::NonUtf8Result))?;
+        let offsets = InstructionOffsets::read(wasm, &self.read_binary_options.features)?;
+        Ok(WabtBuf::from_slice(
+            write_offset_comments(text, &offsets)?.as_bytes(),
+        ))
     }
 }
 
@@ -1117,7 +3094,36 @@ impl Wasm2Wat {
 ///
 pub fn wat2wasm<S: AsRef<[u8]>>(source: S) -> Result<Vec<u8>, Error> {
     let result_buf = Wat2Wasm::new().convert(source)?;
-    Ok(result_buf.as_ref().to_vec())
+    Ok(result_buf.into_vec())
+}
+
+/// Translate wasm text source to wasm binary format, panicking on error.
+///
+/// This is a shorthand for [`wat2wasm`] that is handy for embedding wasm in tests. The
+/// translation happens at runtime and the panic message contains the diagnostic from wabt.
+///
+/// [`wat2wasm`]: fn.wat2wasm.html
+///
+/// # Examples
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate wabt;
+///
+/// fn main() {
+///     let binary: Vec<u8> = wat!("(module)");
+///     assert_eq!(&binary[0..4], &[0, 97, 115, 109]);
+/// }
+/// ```
+///
+#[macro_export]
+macro_rules! wat {
+    ($source:expr) => {
+        match $crate::wat2wasm($source) {
+            Ok(binary) => binary,
+            Err(err) => panic!("failed to translate wat: {}", err),
+        }
+    };
 }
 
 /// Translate wasm text source to wasm binary format.
@@ -1157,22 +3163,71 @@ pub fn wat2wasm_with_features<S: AsRef<[u8]>>(
     source: S,
     features: Features,
 ) -> Result<Vec<u8>, Error> {
-    let mut wat2wasm = Wat2Wasm::new();
-    wat2wasm.features = features;
-    let result_buf = wat2wasm.convert(source)?;
-    Ok(result_buf.as_ref().to_vec())
+    ParseContext::new(features).wat2wasm(source)
 }
 
-/// Disassemble wasm binary to wasm text format.
+/// Translate many modules from wasm text format to binaries in parallel.
+///
+/// The sources are split evenly between as many threads as there are CPUs, each of which
+/// converts its share with a [`ParseContext`] with default features. The results are in
+/// the same order as the sources.
+///
+/// [`ParseContext`]: struct.ParseContext.html
 ///
 /// # Examples
 ///
 /// ```rust
 /// extern crate wabt;
-/// use wabt::wasm2wat;
+/// use wabt::wat2wasm_batch;
 ///
 /// fn main() {
-///     assert_eq!(
+///     let results = wat2wasm_batch(vec!["(module)".to_owned(), "(module".to_owned()]);
+///     assert!(results[0].is_ok());
+///     assert!(results[1].is_err());
+/// }
+/// ```
+///
+pub fn wat2wasm_batch(sources: Vec<String>) -> Vec<Result<Vec<u8>, Error>> {
+    if sources.is_empty() {
+        return Vec::new();
+    }
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+    let chunk_size = (sources.len() + threads - 1) / threads;
+
+    let mut sources = sources.into_iter();
+    let mut handles = Vec::new();
+    loop {
+        let chunk: Vec<String> = sources.by_ref().take(chunk_size).collect();
+        if chunk.is_empty() {
+            break;
+        }
+        handles.push(thread::spawn(move || {
+            let context = ParseContext::new(Features::new());
+            chunk
+                .iter()
+                .map(|source| context.wat2wasm(source))
+                .collect::<Vec<_>>()
+        }));
+    }
+    handles
+        .into_iter()
+        .flat_map(|handle| match handle.join() {
+            Ok(results) => results,
+            Err(panic) => panic::resume_unwind(panic),
+        })
+        .collect()
+}
+
+/// Disassemble wasm binary to wasm text format.
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate wabt;
+/// use wabt::wasm2wat;
+///
+/// fn main() {
+///     assert_eq!(
 ///         wasm2wat(&[
 ///             0, 97, 115, 109, // \0ASM - magic
 ///             1, 0, 0, 0       //    01 - version
@@ -1211,10 +3266,680 @@ pub fn wasm2wat_with_features<S: AsRef<[u8]>>(
     wasm: S,
     features: Features,
 ) -> Result<String, Error> {
-    let result_buf = Wasm2Wat::new().features(features).convert(wasm)?;
-    let text = String::from_utf8(result_buf.as_ref().to_vec())
-        .map_err(|_| Error(ErrorKind::NonUtf8Result))?;
-    Ok(text)
+    ParseContext::new(features).wasm2wat(wasm)
+}
+
+/// Disassemble wasm binary to wasm text format with the given text options.
+///
+/// This makes the options of [`Wasm2Wat`], such as folded expressions, available without
+/// using the builder.
+///
+/// [`Wasm2Wat`]: struct.Wasm2Wat.html
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate wabt;
+/// use wabt::{wasm2wat_with_options, wat2wasm, WriteTextOptions};
+///
+/// fn main() {
+///     let wasm = wat2wasm("(module (func (result i32) i32.const 1))").unwrap();
+///     let mut options = WriteTextOptions::default();
+///     options.fold_exprs(true);
+///     let text = wasm2wat_with_options(&wasm, &options).unwrap();
+///     assert!(text.contains("(i32.const 1)"));
+/// }
+/// ```
+///
+pub fn wasm2wat_with_options<S: AsRef<[u8]>>(
+    wasm: S,
+    options: &WriteTextOptions,
+) -> Result<String, Error> {
+    let module = Module::read_binary(wasm, &ReadBinaryOptions::default())?;
+    let text = module.write_text(options)?;
+    String::from_utf8(text.into_vec()).map_err(|_| Error::from(ErrorKind::NonUtf8Result))
+}
+
+/// Validate a wasm binary.
+///
+/// This is the equivalent of the `wasm-validate` tool: the binary is read and validated,
+/// but nothing is written.
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate wabt;
+/// use wabt::{validate_wasm, Features};
+///
+/// fn main() {
+///     assert!(validate_wasm(&[
+///         0, 97, 115, 109, // \0ASM - magic
+///         1, 0, 0, 0       //    01 - version
+///     ], Features::new()).is_ok());
+/// }
+/// ```
+///
+pub fn validate_wasm<S: AsRef<[u8]>>(wasm: S, features: Features) -> Result<(), Error> {
+    let options = ReadBinaryOptions {
+        features,
+        read_debug_names: false,
+        read_custom_sections: false,
+        stop_on_first_error: true,
+        fail_on_custom_section_error: true,
+    };
+    let module = Module::read_binary(wasm, &options)?;
+    module.validate()
+}
+
+/// Validate a module in the wasm text format.
+///
+/// The source is parsed, names are resolved and the module is validated, but no binary is
+/// written.
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate wabt;
+/// use wabt::{validate_wat, Features};
+///
+/// fn main() {
+///     assert!(validate_wat("(module (func (result i32) i32.const 0))", Features::new()).is_ok());
+///     assert!(validate_wat("(module (func (result i32)))", Features::new()).is_err());
+/// }
+/// ```
+///
+pub fn validate_wat<S: AsRef<[u8]>>(source: S, features: Features) -> Result<(), Error> {
+    let mut module = Module::parse_wat("test.wast", source, features)?;
+    module.resolve_names()?;
+    module.validate()
+}
+
+/// Decompile wasm binary to the C-like pseudo-code of `wasm-decompile`.
+///
+/// Unlike the text format, the output is meant to be read by humans only and can't be
+/// translated back to wasm.
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate wabt;
+/// use wabt::{wasm_decompile, wat2wasm, Features};
+///
+/// fn main() {
+///     let wasm = wat2wasm(
+///         "(module (func (export \"two\") (result i32) i32.const 2))"
+///     ).unwrap();
+///     let text = wasm_decompile(&wasm, Features::new()).unwrap();
+///     assert!(text.contains("function two"));
+/// }
+/// ```
+///
+pub fn wasm_decompile<S: AsRef<[u8]>>(wasm: S, features: Features) -> Result<String, Error> {
+    let options = ReadBinaryOptions {
+        features,
+        read_debug_names: true,
+        read_custom_sections: false,
+        stop_on_first_error: true,
+        fail_on_custom_section_error: true,
+    };
+    let module = Module::read_binary(wasm, &options)?;
+    module.validate()?;
+
+    let raw_buffer = unsafe { ffi::wabt_decompile_module(module.raw_module) };
+    if raw_buffer.is_null() {
+        return Err(Error::from(ErrorKind::Decompile));
+    }
+    String::from_utf8(WabtBuf { raw_buffer }.into_vec())
+        .map_err(|_| Error::from(ErrorKind::NonUtf8Result))
+}
+
+/// Check whether two wasm binaries encode the same module.
+///
+/// Both binaries are read (ignoring debug names and custom sections) and written back
+/// with canonical LEB128 encoding, after which the resulting bytes are compared.
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate wabt;
+/// use wabt::{modules_equivalent, Features, Wat2Wasm};
+///
+/// fn main() {
+///     let source = "(module (func $f))";
+///     let a = Wat2Wasm::new().convert(source).unwrap();
+///     let b = Wat2Wasm::new()
+///         .canonicalize_lebs(false)
+///         .write_debug_names(true)
+///         .convert(source)
+///         .unwrap();
+///     assert!(modules_equivalent(&a, &b, &Features::new()).unwrap());
+/// }
+/// ```
+///
+pub fn modules_equivalent<A: AsRef<[u8]>, B: AsRef<[u8]>>(
+    a: A,
+    b: B,
+    features: &Features,
+) -> Result<bool, Error> {
+    let read_binary_options = ReadBinaryOptions {
+        features: features.clone(),
+        read_debug_names: false,
+        read_custom_sections: false,
+        stop_on_first_error: true,
+        fail_on_custom_section_error: true,
+    };
+    let write_binary_options = WriteBinaryOptions::default();
+
+    let a = Module::read_binary(a, &read_binary_options)?.write_binary(&write_binary_options)?;
+    let b = Module::read_binary(b, &read_binary_options)?.write_binary(&write_binary_options)?;
+    Ok(a.as_ref() == b.as_ref())
+}
+
+/// Rewrite a wasm binary into a deterministic form.
+///
+/// The binary is read without debug names and written back with canonical LEB128
+/// encoding, so binaries encoding the same module are canonicalized to the same bytes.
+/// If `validate` is `true`, the module is validated before it is written, otherwise
+/// invalid modules are rewritten as they are.
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate wabt;
+/// use wabt::{canonicalize_wasm, Wat2Wasm};
+///
+/// fn main() {
+///     let wasm = Wat2Wasm::new()
+///         .canonicalize_lebs(false)
+///         .write_debug_names(true)
+///         .convert("(module (func $f))")
+///         .unwrap();
+///     let canonical = canonicalize_wasm(&wasm, true).unwrap();
+///     assert!(canonical.len() < wasm.as_ref().len());
+/// }
+/// ```
+///
+pub fn canonicalize_wasm<S: AsRef<[u8]>>(wasm: S, validate: bool) -> Result<Vec<u8>, Error> {
+    let module = Module::read_binary(wasm, &ReadBinaryOptions::default())?;
+    if validate {
+        module.validate()?;
+    }
+    let binary = module.write_binary(&WriteBinaryOptions::default())?;
+    Ok(binary.into_vec())
+}
+
+/// Rewrite a module in the wasm text format into a canonical form.
+///
+/// The module is parsed, validated and converted to a binary without names, which is then
+/// written back as text with folded expressions and inline exports. Since only the binary
+/// form survives, formatting, comments, symbolic names and syntactic sugar such as inline
+/// type uses are all normalized away: equivalent inputs produce byte-identical output.
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate wabt;
+/// use wabt::{normalize_wat, Features};
+///
+/// fn main() {
+///     let a = normalize_wat("(module (func $f (export \"f\")))", Features::new()).unwrap();
+///     let b = normalize_wat(
+///         "(module (func) (export \"f\" (func 0)))",
+///         Features::new(),
+///     )
+///     .unwrap();
+///     assert_eq!(a, b);
+/// }
+/// ```
+///
+pub fn normalize_wat<S: AsRef<[u8]>>(source: S, features: Features) -> Result<String, Error> {
+    let mut module = Module::parse_wat("test.wast", source, features.clone())?;
+    module.resolve_names()?;
+    module.validate()?;
+    let binary = module.write_binary(&WriteBinaryOptions::default())?;
+
+    let read_binary_options = ReadBinaryOptions {
+        features,
+        ..ReadBinaryOptions::default()
+    };
+    let write_text_options = WriteTextOptions {
+        fold_exprs: true,
+        inline_export: true,
+        explicit_indices: false,
+    };
+    let module = Module::read_binary(&binary, &read_binary_options)?;
+    let text = module.write_text(&write_text_options)?;
+    String::from_utf8(text.into_vec()).map_err(|_| Error::from(ErrorKind::NonUtf8Result))
+}
+
+/// Reformat a module in the wasm text format, without going through a binary.
+///
+/// The module is parsed, its names are resolved and it's validated, and then it's written
+/// back with `options`. Unlike [`normalize_wat`], the module never becomes a binary, so
+/// symbolic names such as `$f` are kept. Comments are lost though: wabt's parser drops them,
+/// and modules read from a binary never had any to begin with.
+///
+/// [`normalize_wat`]: fn.normalize_wat.html
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate wabt;
+/// use wabt::{reformat_wat, Features, WriteTextOptions};
+///
+/// fn main() {
+///     let mut options = WriteTextOptions::default();
+///     options.fold_exprs(true);
+///     let text = reformat_wat(
+///         "(module (func $f (result i32) i32.const 1 i32.const 2 i32.add))",
+///         Features::new(),
+///         &options,
+///     )
+///     .unwrap();
+///     assert!(text.contains("(func $f"));
+///     assert!(text.contains("(i32.add"));
+/// }
+/// ```
+///
+pub fn reformat_wat<S: AsRef<[u8]>>(
+    source: S,
+    features: Features,
+    options: &WriteTextOptions,
+) -> Result<String, Error> {
+    let mut module = Module::parse_wat("test.wast", source, features)?;
+    module.resolve_names()?;
+    module.validate()?;
+    let text = module.write_text(options)?;
+    String::from_utf8(text.into_vec()).map_err(|_| Error::from(ErrorKind::NonUtf8Result))
+}
+
+/// Compute a hash of the canonical binary form of a module.
+///
+/// The source can be either in the wasm text format or a wasm binary. It is turned into a
+/// module that is written back with canonical LEB128 encoding and without names or custom
+/// sections, like in [`modules_equivalent`], and the bytes are hashed with 64-bit FNV-1a.
+/// So modules that only differ in formatting, names or encoding hash the same.
+///
+/// The hash is only stable for a given wabt version. FNV-1a doesn't depend on the platform,
+/// but the hashed bytes are whatever wabt's binary writer emits, and a newer wabt may order
+/// or encode things differently. Caches keyed on this hash should be invalidated when
+/// `wabt-sys` is updated.
+///
+/// [`modules_equivalent`]: fn.modules_equivalent.html
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate wabt;
+/// use wabt::{canonical_hash, wat2wasm};
+///
+/// fn main() {
+///     let source = "(module (func (export \"f\")))";
+///     let wasm = wat2wasm(source).unwrap();
+///     assert_eq!(canonical_hash(source).unwrap(), canonical_hash(&wasm).unwrap());
+/// }
+/// ```
+///
+pub fn canonical_hash<S: AsRef<[u8]>>(source: S) -> Result<u64, Error> {
+    let source = source.as_ref();
+    let mut module = if source.starts_with(b"\0asm") {
+        Module::read_binary(source, &ReadBinaryOptions::default())?
+    } else {
+        Module::parse_wat("test.wast", source, Features::new())?
+    };
+    module.resolve_names()?;
+    let binary = module.write_binary(&WriteBinaryOptions::default())?;
+
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+    Ok(binary
+        .as_ref()
+        .iter()
+        .fold(FNV_OFFSET_BASIS, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+        }))
+}
+
+/// Kind of a symbol in the linking section of a relocatable binary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolKind {
+    /// Symbol refers to a function.
+    Function,
+    /// Symbol refers to a data segment.
+    Data,
+    /// Symbol refers to a global.
+    Global,
+    /// Symbol refers to a section.
+    Section,
+    /// Symbol refers to an exception tag.
+    Tag,
+    /// Symbol refers to a table.
+    Table,
+}
+
+/// Symbol from the linking section of a relocatable binary.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    /// Name of the symbol. Section symbols don't have a name, so it is empty for them.
+    pub name: String,
+    /// Kind of the item the symbol refers to.
+    pub kind: SymbolKind,
+    /// Symbol flags, such as `WASM_SYM_BINDING_LOCAL` or `WASM_SYM_EXPORTED`.
+    pub flags: u32,
+}
+
+struct ReadLinkingSymbolsResult {
+    raw_result: *mut ffi::WabtReadLinkingSymbolsResult,
+}
+
+impl ReadLinkingSymbolsResult {
+    fn is_ok(&self) -> bool {
+        unsafe {
+            ffi::wabt_read_linking_symbols_result_get_result(self.raw_result) == ffi::Result::Ok
+        }
+    }
+
+    fn symbol_count(&self) -> usize {
+        unsafe { ffi::wabt_read_linking_symbols_result_get_count(self.raw_result) }
+    }
+
+    fn symbol(&self, index: usize) -> Result<Symbol, Error> {
+        assert!(index < self.symbol_count());
+        unsafe {
+            let name = ffi::wabt_read_linking_symbols_result_get_name(self.raw_result, index);
+            let kind = match ffi::wabt_read_linking_symbols_result_get_kind(self.raw_result, index)
+            {
+                0 => SymbolKind::Function,
+                1 => SymbolKind::Data,
+                2 => SymbolKind::Global,
+                3 => SymbolKind::Section,
+                4 => SymbolKind::Tag,
+                5 => SymbolKind::Table,
+                other => {
+                    return Err(Error::from(ErrorKind::Deserialize(format!(
+                        "unknown symbol kind {}",
+                        other
+                    ))))
+                }
+            };
+            Ok(Symbol {
+                name: CStr::from_ptr(name).to_string_lossy().into_owned(),
+                kind,
+                flags: ffi::wabt_read_linking_symbols_result_get_flags(self.raw_result, index),
+            })
+        }
+    }
+}
+
+impl Drop for ReadLinkingSymbolsResult {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::wabt_destroy_read_linking_symbols_result(self.raw_result);
+        }
+    }
+}
+
+/// Read the symbols from the linking section of a relocatable wasm binary.
+///
+/// Relocatable binaries can be produced with [`Wat2Wasm::relocatable`]. For a binary without
+/// a linking section the returned list is empty. Fails if the binary has a symbol of a kind
+/// that [`SymbolKind`] can't represent.
+///
+/// wabt doesn't keep the linking section when reading a binary into a [`Module`], so this
+/// is the way to inspect the symbols of an existing binary. For the symbols a [`Module`]
+/// gets when it is written, see [`Module::linking_symbols`].
+///
+/// [`Wat2Wasm::relocatable`]: struct.Wat2Wasm.html#method.relocatable
+/// [`SymbolKind`]: enum.SymbolKind.html
+/// [`Module`]: struct.Module.html
+/// [`Module::linking_symbols`]: struct.Module.html#method.linking_symbols
+pub fn linking_symbols<S: AsRef<[u8]>>(
+    wasm: S,
+    options: &ReadBinaryOptions,
+) -> Result<Vec<Symbol>, Error> {
+    let errors = Errors::new();
+    let result = {
+        let wasm = wasm.as_ref();
+        let raw_result = unsafe {
+            ffi::wabt_read_linking_symbols(
+                wasm.as_ptr(),
+                wasm.len(),
+                options.features.raw,
+                errors.raw,
+            )
+        };
+        ReadLinkingSymbolsResult { raw_result }
+    };
+    if !result.is_ok() {
+        let msg = String::from_utf8_lossy(errors.format_binary().as_ref()).to_string();
+        return Err(Error::with_diagnostics(
+            ErrorKind::Deserialize(msg),
+            errors.diagnostics(),
+        ));
+    }
+    (0..result.symbol_count())
+        .map(|index| result.symbol(index))
+        .collect()
+}
+
+/// A section of a wasm binary, as listed by `wasm-objdump -h`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SectionInfo {
+    /// Section id, `0` for custom sections.
+    pub id: u8,
+    /// Name of the section, such as `Type` or `Code`. For custom sections this is the
+    /// name stored in the section itself.
+    pub name: String,
+    /// Offset of the section contents from the start of the binary.
+    pub offset: usize,
+    /// Size of the section contents in bytes.
+    pub size: usize,
+}
+
+fn section_name(id: u8) -> &'static str {
+    match id {
+        0 => "Custom",
+        1 => "Type",
+        2 => "Import",
+        3 => "Function",
+        4 => "Table",
+        5 => "Memory",
+        6 => "Global",
+        7 => "Export",
+        8 => "Start",
+        9 => "Elem",
+        10 => "Code",
+        11 => "Data",
+        12 => "DataCount",
+        13 => "Tag",
+        _ => "Unknown",
+    }
+}
+
+/// Read an unsigned LEB128 encoded `u32` at `offset`, returning the value and the
+/// offset just past it.
+fn read_u32_leb128(wasm: &[u8], mut offset: usize) -> Option<(u32, usize)> {
+    let mut result = 0u32;
+    let mut shift = 0;
+    loop {
+        let byte = *wasm.get(offset)?;
+        offset += 1;
+        if shift == 28 && byte & 0xf0 != 0 {
+            return None;
+        }
+        result |= u32::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some((result, offset));
+        }
+        shift += 7;
+    }
+}
+
+/// List the sections of a wasm binary, similar to `wasm-objdump -h`.
+///
+/// Only the section headers are read, so the contents of the sections aren't validated.
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate wabt;
+/// use wabt::{objdump_headers, wat2wasm};
+///
+/// fn main() {
+///     let wasm = wat2wasm("(module (func))").unwrap();
+///     let names: Vec<_> = objdump_headers(&wasm)
+///         .unwrap()
+///         .into_iter()
+///         .map(|section| section.name)
+///         .collect();
+///     assert_eq!(names, ["Type", "Function", "Code"]);
+/// }
+/// ```
+///
+pub fn objdump_headers<S: AsRef<[u8]>>(wasm: S) -> Result<Vec<SectionInfo>, Error> {
+    let sections = read_sections(wasm.as_ref())?;
+    Ok(sections.into_iter().map(|(section, _)| section).collect())
+}
+
+/// Read the section headers of a wasm binary, along with the range of the section contents.
+///
+/// For custom sections the contents start after the section name.
+fn read_sections(wasm: &[u8]) -> Result<Vec<(SectionInfo, Range<usize>)>, Error> {
+    let error = |offset: usize, msg: &str| {
+        Error::from(ErrorKind::Deserialize(format!(
+            "{:07x}: error: {}\n",
+            offset, msg
+        )))
+    };
+
+    if wasm.len() < 8 || wasm[0..4] != *b"\0asm" {
+        return Err(error(0, "bad magic value"));
+    }
+    if wasm[4..8] != [1, 0, 0, 0] {
+        return Err(error(4, "bad wasm file version"));
+    }
+
+    let mut sections = Vec::new();
+    let mut offset = 8;
+    while offset < wasm.len() {
+        let id = wasm[offset];
+        let (size, start) = read_u32_leb128(wasm, offset + 1)
+            .ok_or_else(|| error(offset + 1, "unable to read section size"))?;
+        let size = size as usize;
+        let end = start
+            .checked_add(size)
+            .filter(|&end| end <= wasm.len())
+            .ok_or_else(|| error(start, "invalid section size: extends past end"))?;
+
+        let (name, contents) = if id == 0 {
+            let (name_len, name_start) = read_u32_leb128(wasm, start)
+                .filter(|&(_, name_start)| name_start <= end)
+                .ok_or_else(|| error(start, "unable to read custom section name"))?;
+            let name_end = name_start + name_len as usize;
+            let name = wasm
+                .get(name_start..name_end)
+                .filter(|_| name_end <= end)
+                .and_then(|name| str::from_utf8(name).ok())
+                .ok_or_else(|| error(name_start, "invalid custom section name"))?;
+            (name.to_owned(), name_end..end)
+        } else {
+            (section_name(id).to_owned(), start..end)
+        };
+
+        sections.push((
+            SectionInfo {
+                id,
+                name,
+                offset: start,
+                size,
+            },
+            contents,
+        ));
+        offset = end;
+    }
+    Ok(sections)
+}
+
+/// Relocation from a relocation section of a relocatable binary.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Relocation {
+    /// Index of the section the relocation applies to.
+    pub section: u32,
+    /// Name of the relocation type, such as `R_WASM_FUNCTION_INDEX_LEB`.
+    pub type_name: String,
+    /// Offset of the value to rewrite, relative to the start of the section contents.
+    pub offset: u32,
+    /// Index of the symbol, or of the type for type index relocations.
+    pub index: u32,
+    /// Addend to add to the address. Zero for relocations without an addend.
+    pub addend: i32,
+}
+
+struct ReadRelocationsResult {
+    raw_result: *mut ffi::WabtReadRelocationsResult,
+}
+
+impl ReadRelocationsResult {
+    fn is_ok(&self) -> bool {
+        unsafe { ffi::wabt_read_relocations_result_get_result(self.raw_result) == ffi::Result::Ok }
+    }
+
+    fn relocation_count(&self) -> usize {
+        unsafe { ffi::wabt_read_relocations_result_get_count(self.raw_result) }
+    }
+
+    fn relocation(&self, index: usize) -> Relocation {
+        assert!(index < self.relocation_count());
+        unsafe {
+            let type_name = ffi::wabt_read_relocations_result_get_type_name(self.raw_result, index);
+            Relocation {
+                section: ffi::wabt_read_relocations_result_get_section(self.raw_result, index),
+                type_name: CStr::from_ptr(type_name).to_string_lossy().into_owned(),
+                offset: ffi::wabt_read_relocations_result_get_offset(self.raw_result, index),
+                index: ffi::wabt_read_relocations_result_get_index(self.raw_result, index),
+                addend: ffi::wabt_read_relocations_result_get_addend(self.raw_result, index),
+            }
+        }
+    }
+}
+
+impl Drop for ReadRelocationsResult {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::wabt_destroy_read_relocations_result(self.raw_result);
+        }
+    }
+}
+
+/// Read the relocations from the relocation sections of a relocatable wasm binary.
+///
+/// wabt can produce relocatable binaries with [`Wat2Wasm::relocatable`], but it can't link
+/// them. This exposes what a linker would need to do so. For a binary without relocation
+/// sections the returned list is empty.
+///
+/// [`Wat2Wasm::relocatable`]: struct.Wat2Wasm.html#method.relocatable
+pub fn relocations<S: AsRef<[u8]>>(
+    wasm: S,
+    options: &ReadBinaryOptions,
+) -> Result<Vec<Relocation>, Error> {
+    let errors = Errors::new();
+    let result = {
+        let wasm = wasm.as_ref();
+        let raw_result = unsafe {
+            ffi::wabt_read_relocations(wasm.as_ptr(), wasm.len(), options.features.raw, errors.raw)
+        };
+        ReadRelocationsResult { raw_result }
+    };
+    if !result.is_ok() {
+        let msg = String::from_utf8_lossy(errors.format_binary().as_ref()).to_string();
+        return Err(Error::with_diagnostics(
+            ErrorKind::Deserialize(msg),
+            errors.diagnostics(),
+        ));
+    }
+    Ok((0..result.relocation_count())
+        .map(|index| result.relocation(index))
+        .collect())
 }
 
 struct WabtWriteScriptResult {
@@ -1326,27 +4051,1343 @@ fn features_wasm_features_annotations_disabled() {
 }
 
 #[test]
-fn module() {
+fn features_all() {
+    let features = Features::all();
+    assert!(features.annotations_enabled());
+    assert!(features.extended_const_enabled());
+
+    let example_wat = r#"
+    (module
+        (func (@name "some func") (result i32)
+          i32.const 42
+          return)
+    )"#;
+    assert!(wat2wasm_with_features(example_wat, Features::all()).is_ok());
+}
+
+#[test]
+fn module() {
     let binary_module = wat2wasm(
         r#"
 (module
-  (import "foo" "bar" (func (param f32)))
-  (memory (data "hi"))
-  (type (func (param i32) (result i32)))
-  (start 1)
-  (table 0 1 funcref)
-  (func)
-  (func (type 1)
-    i32.const 42
-    drop)
-  (export "e" (func 1)))
+  (import "foo" "bar" (func (param f32)))
+  (memory (data "hi"))
+  (type (func (param i32) (result i32)))
+  (start 1)
+  (table 0 1 funcref)
+  (func)
+  (func (type 1)
+    i32.const 42
+    drop)
+  (export "e" (func 1)))
+"#,
+    )
+    .unwrap();
+
+    let mut module = Module::read_binary(&binary_module, &ReadBinaryOptions::default()).unwrap();
+    module.resolve_names().unwrap();
+    module.validate().unwrap();
+
+    assert_eq!(module.func_count(), 2);
+    assert_eq!(module.table_count(), 1);
+    assert_eq!(module.memory_count(), 1);
+    assert_eq!(module.global_count(), 0);
+    assert_eq!(module.imports().unwrap().len(), 1);
+}
+
+#[test]
+fn module_write_roundtrip() {
+    let wasm = wat2wasm(
+        r#"
+(module
+  (import "env" "f" (func $f (param i32)))
+  (memory (export "mem") 1)
+  (data (i32.const 0) "hello")
+  (func (export "g") (param i32)
+    local.get 0
+    call $f))
+"#,
+    )
+    .unwrap();
+
+    let module = Module::read_binary(&wasm, &ReadBinaryOptions::default()).unwrap();
+    let written = module.write_binary(&WriteBinaryOptions::default()).unwrap();
+    assert_eq!(written.as_ref(), &wasm[..]);
+
+    let text = module.write_text(&WriteTextOptions::default()).unwrap();
+    assert_eq!(
+        str::from_utf8(text.as_ref()).unwrap(),
+        wasm2wat(&wasm).unwrap()
+    );
+}
+
+#[test]
+fn module_resolve_names() {
+    let wasm = wat2wasm(r#"(module (func $f) (func (export "g") call $f))"#).unwrap();
+    let mut module = Module::read_binary(&wasm, &ReadBinaryOptions::default()).unwrap();
+    module.resolve_names().unwrap();
+    module.validate().unwrap();
+
+    let mut module =
+        Module::parse_wat_str("(module (func $f) (start $f))", Features::new()).unwrap();
+    module.resolve_names().unwrap();
+    module.validate().unwrap();
+
+    let mut module = Module::parse_wat_str("(module (start $missing))", Features::new()).unwrap();
+    match module.resolve_names() {
+        Err(err) => match *err.kind() {
+            ErrorKind::ResolveNames(_) => {}
+            ref other => panic!("unexpected error: {:?}", other),
+        },
+        Ok(()) => panic!("expected an error"),
+    }
+}
+
+#[test]
+fn module_try_from() {
+    let module = Module::try_from("(module (func (export \"f\")))").unwrap();
+    module.validate().unwrap();
+    assert_eq!(module.exports().unwrap()[0].name, "f");
+    assert!(Module::try_from("(module (func").is_err());
+
+    let wasm = wat2wasm("(module (func (export \"f\")))").unwrap();
+    let module = Module::try_from(&wasm[..]).unwrap();
+    module.validate().unwrap();
+    assert_eq!(module.exports().unwrap()[0].name, "f");
+
+    // Bytes are always read as a binary.
+    assert!(Module::try_from(&b"(module)"[..]).is_err());
+}
+
+#[test]
+fn module_exports() {
+    let wat = r#"
+(module
+  (import "foo" "bar" (func (param f32)))
+  (memory (export "mem") 1)
+  (func)
+  (func (param i32) (result i32)
+    local.get 0)
+  (export "e" (func 1))
+  (export "id" (func $id))
+  (func $id (export "id2") (param f64) (result f64)
+    local.get 0))
+"#;
+    let func = |params: Vec<ValueType>, results: Vec<ValueType>| {
+        ExportKind::Func(FuncType { params, results })
+    };
+    let expected = vec![
+        ModuleExport {
+            name: "mem".to_owned(),
+            kind: ExportKind::Memory,
+            index: 0,
+        },
+        ModuleExport {
+            name: "e".to_owned(),
+            kind: func(Vec::new(), Vec::new()),
+            index: 1,
+        },
+        ModuleExport {
+            name: "id".to_owned(),
+            kind: func(vec![ValueType::F64], vec![ValueType::F64]),
+            index: 3,
+        },
+        ModuleExport {
+            name: "id2".to_owned(),
+            kind: func(vec![ValueType::F64], vec![ValueType::F64]),
+            index: 3,
+        },
+    ];
+
+    let module = Module::parse_wat("test.wast", wat, Features::new()).unwrap();
+    assert_eq!(module.exports().unwrap(), expected);
+
+    let wasm = wat2wasm(wat).unwrap();
+    let module = Module::read_binary(&wasm, &ReadBinaryOptions::default()).unwrap();
+    assert_eq!(module.exports().unwrap(), expected);
+}
+
+#[test]
+fn module_exports_unknown_item() {
+    let module = Module::parse_wat(
+        "test.wast",
+        r#"(module (export "e" (func $missing)))"#,
+        Features::new(),
+    )
+    .unwrap();
+    assert_eq!(
+        module.exports(),
+        Err(Error::from(ErrorKind::UnknownExportItem("e".to_owned())))
+    );
+}
+
+#[test]
+fn module_parse_wat_str() {
+    let module = Module::parse_wat_str("(module (func))", Features::new()).unwrap();
+    module.validate().unwrap();
+
+    match Module::parse_wat_str("(module (func", Features::new()) {
+        Err(err) => assert_eq!(err.location().unwrap().filename, "<buffer>"),
+        Ok(_) => panic!("expected a parse error"),
+    }
+}
+
+#[test]
+fn module_parse_wat_owned() {
+    let source = r#"(module (func (export "f") (result i32) i32.const 42))"#;
+    let owned = Module::parse_wat_owned("test.wast", source.into(), Features::new()).unwrap();
+    owned.validate().unwrap();
+    let borrowed = Module::parse_wat("test.wast", source, Features::new()).unwrap();
+    assert_eq!(
+        owned
+            .write_binary(&WriteBinaryOptions::default())
+            .unwrap()
+            .as_ref(),
+        borrowed
+            .write_binary(&WriteBinaryOptions::default())
+            .unwrap()
+            .as_ref()
+    );
+
+    let err = Module::parse_wat_owned(
+        "test.wast",
+        b"(module (func i32.add))".to_vec(),
+        Features::new(),
+    )
+    .unwrap()
+    .validate()
+    .unwrap_err();
+    assert!(err.message().unwrap().contains("i32.add"));
+}
+
+#[test]
+fn module_custom_sections() {
+    let mut binary = wat2wasm("(module)").unwrap();
+    // A `producers` section with a single `language` field of `Rust 1.0`.
+    let mut contents = vec![9];
+    contents.extend_from_slice(b"producers");
+    contents.extend_from_slice(&[1, 8]);
+    contents.extend_from_slice(b"language");
+    contents.extend_from_slice(&[1, 4]);
+    contents.extend_from_slice(b"Rust");
+    contents.extend_from_slice(&[3]);
+    contents.extend_from_slice(b"1.0");
+    binary.push(0);
+    binary.push(contents.len() as u8);
+    binary.extend_from_slice(&contents);
+
+    let module = Module::read_binary(&binary, &ReadBinaryOptions::default()).unwrap();
+    assert!(module.custom_sections().is_empty());
+
+    let mut options = ReadBinaryOptions::default();
+    options.read_custom_sections(true);
+    let module = Module::read_binary(&binary, &options).unwrap();
+    assert_eq!(
+        module.custom_sections(),
+        &[("producers".to_owned(), contents[10..].to_vec())]
+    );
+}
+
+#[test]
+fn read_binary_malformed_custom_section() {
+    let mut binary = wat2wasm("(module (func))").unwrap();
+    // A name section whose function names subsection claims to be longer than it is.
+    binary.extend_from_slice(&[0, 8, 4]);
+    binary.extend_from_slice(b"name");
+    binary.extend_from_slice(&[1, 5, 0xff]);
+
+    let mut options = ReadBinaryOptions {
+        read_debug_names: true,
+        ..ReadBinaryOptions::default()
+    };
+    let err = match Module::read_binary(&binary, &options) {
+        Ok(_) => panic!("expected an error"),
+        Err(err) => err,
+    };
+    assert!(!err.diagnostics().is_empty());
+    assert!(err
+        .diagnostics()
+        .iter()
+        .all(|diagnostic| diagnostic.severity == Severity::Error));
+
+    options.fail_on_custom_section_error(false);
+    let module = Module::read_binary(&binary, &options).unwrap();
+    module.validate().unwrap();
+    let warnings = module.warnings();
+    assert!(!warnings.is_empty());
+    for warning in warnings {
+        assert_eq!(warning.severity, Severity::Warning);
+        assert!(warning.offset.is_some());
+    }
+
+    let module = Module::read_binary(wat2wasm("(module (func))").unwrap(), &options).unwrap();
+    assert!(module.warnings().is_empty());
+}
+
+#[test]
+fn module_read_binary_prefix() {
+    let wasm = wat2wasm("(module (func (export \"f\")))").unwrap();
+    let options = ReadBinaryOptions::default();
+
+    let (module, consumed) = Module::read_binary_prefix(&wasm, &options).unwrap();
+    assert_eq!(consumed, wasm.len());
+    module.validate().unwrap();
+
+    let mut with_trailing = wasm.clone();
+    with_trailing.extend_from_slice(&[0xff, 0x00, 0x61, 0x73, 0x6d]);
+    assert!(Module::read_binary(&with_trailing, &options).is_err());
+    let (module, consumed) = Module::read_binary_prefix(&with_trailing, &options).unwrap();
+    assert_eq!(consumed, wasm.len());
+    module.validate().unwrap();
+
+    assert!(Module::read_binary_prefix(b"not wasm", &options).is_err());
+}
+
+#[test]
+fn module_read_binary_prefix_concatenated() {
+    let first = wat2wasm(
+        r#"
+(module
+  (memory 1)
+  (func (export "f") (result i32)
+    i32.const 1)
+  (data (i32.const 0) "first"))
+"#,
+    )
+    .unwrap();
+    let second = wat2wasm(
+        r#"
+(module
+  (func (export "g") (result i64)
+    i64.const 2))
+"#,
+    )
+    .unwrap();
+    let mut concatenated = first.clone();
+    concatenated.extend_from_slice(&second);
+    let options = ReadBinaryOptions::default();
+
+    let (module, consumed) = Module::read_binary_prefix(&concatenated, &options).unwrap();
+    assert_eq!(consumed, first.len());
+    assert_eq!(
+        module
+            .write_binary(&WriteBinaryOptions::default())
+            .unwrap()
+            .as_ref(),
+        &first[..]
+    );
+
+    let (module, consumed) =
+        Module::read_binary_prefix(&concatenated[consumed..], &options).unwrap();
+    assert_eq!(consumed, second.len());
+    assert_eq!(
+        module
+            .write_binary(&WriteBinaryOptions::default())
+            .unwrap()
+            .as_ref(),
+        &second[..]
+    );
+}
+
+#[test]
+fn error_kind_code() {
+    let nul_error = CString::new("\0").unwrap_err();
+    let kinds = vec![
+        ErrorKind::Nul(nul_error),
+        ErrorKind::Deserialize(String::new()),
+        ErrorKind::Parse(String::new()),
+        ErrorKind::WriteText,
+        ErrorKind::NonUtf8Result,
+        ErrorKind::WriteBinary,
+        ErrorKind::ResolveNames(String::new()),
+        ErrorKind::Validate(String::new()),
+        ErrorKind::GenerateNames(String::new()),
+        ErrorKind::ApplyNames(String::new()),
+        ErrorKind::InvalidFunctionIndex(0),
+        ErrorKind::Decompile,
+        ErrorKind::UnknownValueType(String::new()),
+        ErrorKind::UnknownExport(String::new()),
+        ErrorKind::DuplicateExport(String::new()),
+        ErrorKind::Io(io::Error::from(io::ErrorKind::InvalidData)),
+        ErrorKind::WriteOffsets(String::new()),
+        ErrorKind::UnknownExportItem(String::new()),
+    ];
+    let codes: Vec<u32> = kinds
+        .into_iter()
+        .map(|kind| Error::from(kind).kind_code())
+        .collect();
+    assert_eq!(codes, (1..=18).collect::<Vec<_>>());
+
+    // The code doesn't depend on the message.
+    let err = wat2wasm("(module (func (result i32)))").unwrap_err();
+    assert_eq!(err.kind_code(), 8);
+    assert_eq!(
+        Error::from(ErrorKind::Validate("other".to_owned())).kind_code(),
+        8
+    );
+}
+
+#[test]
+fn test_wabt_version() {
+    let version = wabt_version();
+    assert!(!version.is_empty());
+    let numbers: Vec<_> = version
+        .split(|c: char| !c.is_ascii_digit() && c != '.')
+        .next()
+        .unwrap()
+        .split('.')
+        .collect();
+    assert!(numbers.len() >= 2, "{}", version);
+    for number in numbers {
+        number.parse::<u32>().unwrap();
+    }
+}
+
+#[test]
+fn value_type_names() {
+    let types = [
+        (ValueType::I32, "i32"),
+        (ValueType::I64, "i64"),
+        (ValueType::F32, "f32"),
+        (ValueType::F64, "f64"),
+        (ValueType::V128, "v128"),
+        (ValueType::FuncRef, "funcref"),
+        (ValueType::ExternRef, "externref"),
+    ];
+    for &(value_type, name) in &types {
+        assert_eq!(value_type.to_string(), name);
+        assert_eq!(name.parse::<ValueType>().unwrap(), value_type);
+    }
+
+    let err = "i31ref".parse::<ValueType>().unwrap_err();
+    assert_eq!(
+        err,
+        Error::from(ErrorKind::UnknownValueType("i31ref".to_owned()))
+    );
+    assert_eq!(err.to_string(), "unknown value type 'i31ref'");
+}
+
+#[test]
+fn func_type_display() {
+    let func_type = FuncType {
+        params: vec![ValueType::I32, ValueType::I32],
+        results: vec![ValueType::I32],
+    };
+    assert_eq!(func_type.to_string(), "(param i32 i32) (result i32)");
+
+    let func_type = FuncType {
+        params: Vec::new(),
+        results: vec![ValueType::F64, ValueType::ExternRef],
+    };
+    assert_eq!(func_type.to_string(), "(result f64 externref)");
+
+    assert_eq!(FuncType::default().to_string(), "");
+}
+
+#[test]
+fn module_types() {
+    let wat = r#"
+        (module
+            (type (func (param i32 i32) (result i32)))
+            (func (param f64) (result funcref)
+                ref.null func)
+            (func (type 0)
+                local.get 0))
+    "#;
+    let mut features = Features::new();
+    features.enable_reference_types();
+    let module = Module::parse_wat("test.wast", wat, features.clone()).unwrap();
+    assert_eq!(
+        module.types().unwrap(),
+        vec![
+            FuncType {
+                params: vec![ValueType::I32, ValueType::I32],
+                results: vec![ValueType::I32],
+            },
+            FuncType {
+                params: vec![ValueType::F64],
+                results: vec![ValueType::FuncRef],
+            },
+        ]
+    );
+
+    let wasm = wat2wasm_with_features(wat, features.clone()).unwrap();
+    let options = ReadBinaryOptions {
+        features,
+        ..ReadBinaryOptions::default()
+    };
+    let module = Module::read_binary(&wasm, &options).unwrap();
+    assert_eq!(module.types().unwrap().len(), 2);
+}
+
+#[test]
+fn module_imports() {
+    let wat = r#"
+        (module
+            (import "env" "log" (func (param i32 f64)))
+            (import "env" "memory" (memory 1 16)))
+    "#;
+    let expected = vec![
+        Import {
+            module: "env".to_owned(),
+            field: "log".to_owned(),
+            kind: ImportKind::Func(FuncType {
+                params: vec![ValueType::I32, ValueType::F64],
+                results: Vec::new(),
+            }),
+        },
+        Import {
+            module: "env".to_owned(),
+            field: "memory".to_owned(),
+            kind: ImportKind::Memory {
+                limits: Limits {
+                    initial: 1,
+                    max: Some(16),
+                },
+            },
+        },
+    ];
+
+    let module = Module::parse_wat("test.wast", wat, Features::new()).unwrap();
+    assert_eq!(module.imports().unwrap(), expected);
+
+    let wasm = wat2wasm(wat).unwrap();
+    let module = Module::read_binary(&wasm, &ReadBinaryOptions::default()).unwrap();
+    assert_eq!(module.imports().unwrap(), expected);
+}
+
+#[test]
+fn module_data_segments() {
+    let wat = r#"
+        (module
+            (memory 1)
+            (global $base i32 (i32.const 16))
+            (data "hi")
+            (data (i32.const 8) "\01\02")
+            (data (global.get $base) ""))
+    "#;
+    let mut features = Features::new();
+    features.enable_bulk_memory();
+    let expected = vec![
+        DataSegment {
+            mode: DataSegmentMode::Passive,
+            data: b"hi".to_vec(),
+        },
+        DataSegment {
+            mode: DataSegmentMode::Active {
+                memory: 0,
+                offset: OffsetExpr::I32Const(8),
+            },
+            data: vec![1, 2],
+        },
+        DataSegment {
+            mode: DataSegmentMode::Active {
+                memory: 0,
+                offset: OffsetExpr::GlobalGet(0),
+            },
+            data: Vec::new(),
+        },
+    ];
+
+    let module = Module::parse_wat("test.wast", wat, features.clone()).unwrap();
+    assert_eq!(module.data_segments(), expected);
+
+    let wasm = wat2wasm_with_features(wat, features.clone()).unwrap();
+    let options = ReadBinaryOptions {
+        features,
+        ..ReadBinaryOptions::default()
+    };
+    let module = Module::read_binary(&wasm, &options).unwrap();
+    assert_eq!(module.data_segments(), expected);
+}
+
+#[test]
+fn module_rename_export() {
+    let wasm = wat2wasm(r#"(module (func (export "_start")) (func (export "helper")))"#).unwrap();
+    let mut module = Module::read_binary(&wasm, &ReadBinaryOptions::default()).unwrap();
+    module.rename_export("_start", "main").unwrap();
+    assert_eq!(
+        module.rename_export("_start", "other"),
+        Err(Error::from(ErrorKind::UnknownExport("_start".to_owned())))
+    );
+    assert_eq!(
+        module.rename_export("main", "helper"),
+        Err(Error::from(ErrorKind::DuplicateExport("helper".to_owned())))
+    );
+
+    let wasm = module.write_binary(&WriteBinaryOptions::default()).unwrap();
+    let module = Module::read_binary(&wasm, &ReadBinaryOptions::default()).unwrap();
+    let names: Vec<_> = module
+        .exports()
+        .unwrap()
+        .into_iter()
+        .map(|export| export.name)
+        .collect();
+    assert_eq!(names, ["main", "helper"]);
+}
+
+#[test]
+fn module_add_custom_section() {
+    let mut module = Module::parse_wat_str("(module (func))", Features::new()).unwrap();
+    module.add_custom_section("producers", b"\x00").unwrap();
+    module.add_custom_section("meta", b"first").unwrap();
+    module.add_custom_section("meta", b"second").unwrap();
+    assert!(module.add_custom_section("nul\0", b"").is_err());
+
+    let wasm = module.write_binary(&WriteBinaryOptions::default()).unwrap();
+    let mut options = ReadBinaryOptions::default();
+    options.read_custom_sections(true);
+    let module = Module::read_binary(&wasm, &options).unwrap();
+    assert_eq!(
+        module.custom_sections(),
+        &[
+            ("producers".to_owned(), b"\x00".to_vec()),
+            ("meta".to_owned(), b"first".to_vec()),
+            ("meta".to_owned(), b"second".to_vec()),
+        ][..]
+    );
+}
+
+#[test]
+fn wat2wasm_max_memory_pages() {
+    let wat = "(module (memory 1 100000))";
+    assert!(Wat2Wasm::new().convert(wat).is_err());
+
+    let wasm = Wat2Wasm::new()
+        .max_memory_pages(Some(100))
+        .convert(wat)
+        .unwrap();
+    let module = Module::read_binary(&wasm, &ReadBinaryOptions::default()).unwrap();
+    module.validate().unwrap();
+    let text = wasm2wat(&wasm).unwrap();
+    assert!(text.contains("(memory (;0;) 1 100)"), "{}", text);
+
+    let wasm = Wat2Wasm::new()
+        .max_memory_pages(Some(100))
+        .convert("(module (memory 200))")
+        .unwrap();
+    let text = wasm2wat(&wasm).unwrap();
+    assert!(text.contains("(memory (;0;) 100)"), "{}", text);
+}
+
+#[test]
+fn test_reformat_wat() {
+    let messy = r#"
+        (module   (func   $add (export "add")
+          (param $a i32) (param $b i32)   (result i32)
+              local.get $a   ;; first
+          local.get $b
+            i32.add
+        ))
+    "#;
+    let mut options = WriteTextOptions::default();
+    options.fold_exprs(true);
+    let text = reformat_wat(messy, Features::new(), &options).unwrap();
+    assert!(text.starts_with("(module\n  "), "{}", text);
+    assert!(text.contains("(func $add"), "{}", text);
+    assert!(
+        text.contains("(i32.add\n      (local.get $a)\n      (local.get $b))"),
+        "{}",
+        text
+    );
+    assert!(!text.contains(";; first"), "{}", text);
+
+    // Reformatting is idempotent.
+    assert_eq!(
+        reformat_wat(&text, Features::new(), &options).unwrap(),
+        text
+    );
+
+    assert!(reformat_wat("(module (func (result i32)))", Features::new(), &options).is_err());
+}
+
+#[test]
+fn test_reformat_wat_drops_comments() {
+    // wabt's parser doesn't keep comments, so none of them survive reformatting. This pins
+    // that behaviour: if it ever changes, the docs of `reformat_wat` need to change too.
+    let source = r#"
+        ;; leading line comment
+        (; leading block comment ;)
+        (module
+          (func $f (result i32) ;; trailing line comment
+            (; inline (; nested ;) block comment ;)
+            i32.const 1))
+    "#;
+    let text = reformat_wat(source, Features::new(), &WriteTextOptions::default()).unwrap();
+    for comment in &[
+        "leading line comment",
+        "leading block comment",
+        "trailing line comment",
+        "inline",
+        "nested",
+    ] {
+        assert!(!text.contains(comment), "{}", text);
+    }
+    assert!(text.contains("(func $f (result i32)"), "{}", text);
+}
+
+#[test]
+fn test_canonical_hash() {
+    let a = canonical_hash("(module (func $f (result i32) i32.const 42))").unwrap();
+    let b = canonical_hash(
+        r#"
+        (module
+            (func
+                (result i32)
+                (i32.const 42)))
+        "#,
+    )
+    .unwrap();
+    assert_eq!(a, b);
+
+    let c = canonical_hash("(module (func (result i32) i32.const 43))").unwrap();
+    assert_ne!(a, c);
+
+    let wasm = Wat2Wasm::new()
+        .canonicalize_lebs(false)
+        .convert("(module (func (result i32) i32.const 42))")
+        .unwrap();
+    assert_eq!(canonical_hash(&wasm).unwrap(), a);
+
+    // FNV-1a of the canonical binary:
+    //   00 61 73 6d 01 00 00 00        magic and version
+    //   01 05 01 60 00 01 7f           type section
+    //   03 02 01 00                    function section
+    //   0a 06 01 04 00 41 2a 0b        code section
+    // If this changes, wabt writes a different binary and hashes computed before are stale.
+    assert_eq!(a, 0x917f_e97e_479a_5c15);
+}
+
+#[test]
+fn test_normalize_wat() {
+    let a = normalize_wat(
+        r#"(module (func $add (export "add") (param i32 i32) (result i32) local.get 0 local.get 1 i32.add))"#,
+        Features::new(),
+    )
+    .unwrap();
+    let b = normalize_wat(
+        r#"
+        ;; The same module, written differently.
+        (module
+            (type $t (func (param i32 i32) (result i32)))
+            (func (type $t)
+                (i32.add
+                    (local.get 0)
+                    (local.get 1)))
+            (export "add" (func 0)))
+        "#,
+        Features::new(),
+    )
+    .unwrap();
+    assert_eq!(a, b);
+    assert!(a.contains("(export \"add\")"), "{}", a);
+    assert!(a.contains("(i32.add"), "{}", a);
+
+    assert!(normalize_wat("(module (func (result i32)))", Features::new()).is_err());
+}
+
+#[test]
+fn test_canonicalize_wasm() {
+    let source = "(module (func (export \"f\") (result i32) i32.const 1))";
+    let canonical = wat2wasm(source).unwrap();
+    let padded = Wat2Wasm::new()
+        .canonicalize_lebs(false)
+        .convert(source)
+        .unwrap()
+        .into_vec();
+    assert!(padded.len() > canonical.len());
+
+    assert_eq!(canonicalize_wasm(&padded, true).unwrap(), canonical);
+    assert_eq!(canonicalize_wasm(&canonical, true).unwrap(), canonical);
+
+    let invalid = Wat2Wasm::new()
+        .validate(false)
+        .convert("(module (func (result i32)))")
+        .unwrap();
+    assert!(canonicalize_wasm(&invalid, true).is_err());
+    assert!(canonicalize_wasm(&invalid, false).is_ok());
+}
+
+#[test]
+fn module_generate_names() {
+    let binary_module = wat2wasm(
+        r#"
+(module
+  (func (param i32) (result i32)
+    local.get 0))
+"#,
+    )
+    .unwrap();
+
+    let mut module = Module::read_binary(&binary_module, &ReadBinaryOptions::default()).unwrap();
+    let text = module.write_text(&WriteTextOptions::default()).unwrap();
+    assert!(!text.as_ref().contains(&b'$'));
+
+    module.generate_names().unwrap();
+    let text = module.write_text(&WriteTextOptions::default()).unwrap();
+    assert!(text.as_ref().contains(&b'$'));
+}
+
+#[test]
+fn module_apply_names() {
+    let mut module = Module::parse_wat(
+        "test.wast",
+        r#"
+(module
+  (func $callee)
+  (func $caller
+    call $callee))
+"#,
+        Features::new(),
+    )
+    .unwrap();
+    module.resolve_names().unwrap();
+    module.apply_names().unwrap();
+
+    let text = module.write_text(&WriteTextOptions::default()).unwrap();
+    let text = String::from_utf8(text.as_ref().to_vec()).unwrap();
+    assert!(text.contains("call $callee"));
+}
+
+#[test]
+fn module_apply_names_unknown_item() {
+    let mut module = Module::parse_wat(
+        "test.wast",
+        r#"
+(module
+  (func
+    call 5))
+"#,
+        Features::new(),
+    )
+    .unwrap();
+    module.resolve_names().unwrap();
+
+    let err = module.apply_names().unwrap_err();
+    match *err.kind() {
+        ErrorKind::ApplyNames(ref msg) => assert!(!msg.is_empty()),
+        ref kind => panic!("unexpected error kind: {:?}", kind),
+    }
+}
+
+#[test]
+fn name_section_levels() {
+    let source = r#"
+(module
+  (func $callee (param $first_param i32)))
+"#;
+    let contains =
+        |binary: &[u8], needle: &[u8]| binary.windows(needle.len()).any(|window| window == needle);
+
+    let none = Wat2Wasm::new()
+        .name_section(NameSectionLevel::None)
+        .convert(source)
+        .unwrap();
+    assert!(!contains(none.as_ref(), b"callee"));
+    assert!(!contains(none.as_ref(), b"first_param"));
+
+    let functions_only = Wat2Wasm::new()
+        .name_section(NameSectionLevel::FunctionsOnly)
+        .convert(source)
+        .unwrap();
+    assert!(contains(functions_only.as_ref(), b"callee"));
+    assert!(!contains(functions_only.as_ref(), b"first_param"));
+
+    let full = Wat2Wasm::new()
+        .name_section(NameSectionLevel::Full)
+        .convert(source)
+        .unwrap();
+    assert!(contains(full.as_ref(), b"callee"));
+    assert!(contains(full.as_ref(), b"first_param"));
+}
+
+#[test]
+fn relocatable_linking_symbols() {
+    let binary = Wat2Wasm::new()
+        .relocatable(true)
+        .convert(
+            r#"
+(module
+  (func $answer (export "answer") (result i32)
+    i32.const 42))
+"#,
+        )
+        .unwrap();
+
+    let symbols = linking_symbols(&binary, &ReadBinaryOptions::default()).unwrap();
+    assert!(symbols
+        .iter()
+        .any(|symbol| symbol.kind == SymbolKind::Function && symbol.name.contains("answer")));
+}
+
+#[test]
+fn module_linking_symbols() {
+    let source = r#"
+(module
+  (func $answer (export "answer") (result i32)
+    i32.const 42))
+"#;
+    let mut module = Module::parse_wat("test.wast", source, Features::new()).unwrap();
+    module.resolve_names().unwrap();
+
+    let symbols = module.linking_symbols().unwrap();
+    assert!(symbols
+        .iter()
+        .any(|symbol| symbol.kind == SymbolKind::Function && symbol.name.contains("answer")));
+
+    let binary = Wat2Wasm::new().relocatable(true).convert(source).unwrap();
+    assert_eq!(
+        symbols,
+        linking_symbols(&binary, &ReadBinaryOptions::default()).unwrap()
+    );
+}
+
+#[test]
+fn relocatable_relocations() {
+    let binary = Wat2Wasm::new()
+        .relocatable(true)
+        .convert(
+            r#"
+(module
+  (import "env" "callee" (func $callee (result i32)))
+  (func $caller (export "caller") (result i32)
+    call $callee))
+"#,
+        )
+        .unwrap();
+
+    let relocs = relocations(&binary, &ReadBinaryOptions::default()).unwrap();
+    let call = relocs
+        .iter()
+        .find(|reloc| reloc.type_name == "R_WASM_FUNCTION_INDEX_LEB")
+        .expect("call should be relocated");
+
+    // The relocated index refers to the symbol of the imported function.
+    let symbols = linking_symbols(&binary, &ReadBinaryOptions::default()).unwrap();
+    assert_eq!(symbols[call.index as usize].kind, SymbolKind::Function);
+    assert!(symbols[call.index as usize].name.contains("callee"));
+
+    let plain = wat2wasm("(module (func))").unwrap();
+    assert_eq!(
+        relocations(&plain, &ReadBinaryOptions::default()),
+        Ok(vec![])
+    );
+}
+
+#[test]
+fn wat2wasm_pipeline_toggles() {
+    let source = "(module (func (result i32) i32.const 0))";
+    let expected = wat2wasm(source).unwrap();
+    for &resolve_names in &[true, false] {
+        for &validate in &[true, false] {
+            let binary = Wat2Wasm::new()
+                .resolve_names(resolve_names)
+                .validate(validate)
+                .convert(source)
+                .unwrap();
+            assert_eq!(binary.as_ref(), &expected[..]);
+        }
+    }
+
+    let invalid = "(module (func (result i32)))";
+    for &resolve_names in &[true, false] {
+        let mut wat2wasm = Wat2Wasm::new();
+        wat2wasm.resolve_names(resolve_names);
+        assert!(wat2wasm.validate(true).convert(invalid).is_err());
+        assert!(wat2wasm.validate(false).convert(invalid).is_ok());
+    }
+}
+
+#[test]
+fn test_modules_equivalent() {
+    let source = r#"
+(module
+  (func $add (param i32 i32) (result i32)
+    local.get 0
+    local.get 1
+    i32.add))
+"#;
+    let canonical = wat2wasm(source).unwrap();
+    let relaxed = Wat2Wasm::new()
+        .canonicalize_lebs(false)
+        .write_debug_names(true)
+        .convert(source)
+        .unwrap();
+    assert_ne!(&canonical[..], relaxed.as_ref());
+    assert!(modules_equivalent(&canonical, &relaxed, &Features::new()).unwrap());
+
+    let different = wat2wasm(source.replace("i32.add", "i32.sub")).unwrap();
+    assert!(!modules_equivalent(&canonical, &different, &Features::new()).unwrap());
+}
+
+#[test]
+fn module_function_text() {
+    let binary = wat2wasm(
+        r#"
+(module
+  (import "env" "f" (func (param i32)))
+  (func (result i32)
+    i32.const 1
+    i32.const 2
+    i32.add)
+  (func (result i32)
+    i32.const 3
+    i32.const 4
+    i32.sub))
 "#,
     )
     .unwrap();
+    let module = Module::read_binary(&binary, &ReadBinaryOptions::default()).unwrap();
 
-    let mut module = Module::read_binary(&binary_module, &ReadBinaryOptions::default()).unwrap();
-    module.resolve_names().unwrap();
+    let text = module.function_text(2).unwrap();
+    assert!(text.starts_with("(func"));
+    assert!(text.contains("i32.sub"));
+    assert!(!text.contains("i32.add"));
+
+    assert_eq!(
+        module.function_text(0),
+        Err(Error::from(ErrorKind::InvalidFunctionIndex(0)))
+    );
+    assert_eq!(
+        module.function_text(3),
+        Err(Error::from(ErrorKind::InvalidFunctionIndex(3)))
+    );
+}
+
+#[test]
+fn module_function_texts() {
+    let binary = wat2wasm(
+        r#"
+(module
+  (import "env" "f" (func (param i32)))
+  (func (result i32)
+    i32.const 1
+    i32.const 2
+    i32.add)
+  (func (result i32)
+    i32.const 3
+    i32.const 4
+    i32.sub))
+"#,
+    )
+    .unwrap();
+    let module = Module::read_binary(&binary, &ReadBinaryOptions::default()).unwrap();
+
+    let texts = module.function_texts().unwrap();
+    let indices: Vec<u32> = texts.iter().map(|&(index, _)| index).collect();
+    assert_eq!(indices, vec![1, 2]);
+    for (index, text) in texts {
+        assert_eq!(text, module.function_text(index).unwrap());
+    }
+    assert!(module.function_texts().unwrap()[0].1.contains("i32.add"));
+}
+
+#[test]
+fn parse_context() {
+    let sources = [
+        "(module)",
+        "(module (func (export \"f\") (result i32) i32.const 1))",
+        "(modu",
+        "(module (memory 1) (data (i32.const 0) \"hi\"))",
+        "(module (func $f (param i32)))",
+    ];
+
+    let context = ParseContext::new(Features::new());
+    for _ in 0..100 {
+        for source in &sources {
+            let expected = Module::parse_wat("test.wast", source, Features::new())
+                .and_then(|module| module.write_binary(&WriteBinaryOptions::default()));
+            let actual = context
+                .parse_wat("test.wast", source)
+                .and_then(|module| module.write_binary(&WriteBinaryOptions::default()));
+            let owned = context
+                .parse_wat_owned("test.wast", source.as_bytes().to_vec())
+                .and_then(|module| module.write_binary(&WriteBinaryOptions::default()));
+            assert_eq!(
+                actual.as_ref().map(|binary| binary.as_ref()),
+                owned.as_ref().map(|binary| binary.as_ref())
+            );
+            match (expected, actual) {
+                (Ok(expected), Ok(actual)) => assert_eq!(expected.as_ref(), actual.as_ref()),
+                (Err(expected), Err(actual)) => {
+                    assert_eq!(expected, actual);
+                    assert_eq!(expected.diagnostics(), actual.diagnostics());
+                }
+                _ => panic!("results differ for {}", source),
+            }
+        }
+    }
+}
+
+#[test]
+fn parse_context_convert() {
+    let context = ParseContext::new(Features::new());
+    let sources = [
+        "(module)",
+        "(module (func (export \"f\") (result i32) i32.const 1))",
+    ];
+    for source in &sources {
+        let binary = context.wat2wasm(source).unwrap();
+        assert_eq!(binary, wat2wasm(source).unwrap());
+        assert_eq!(context.wasm2wat(&binary), wasm2wat(&binary));
+    }
+
+    // An error doesn't leak into the next conversion.
+    let err = context.wat2wasm("(module (func i32.add))").unwrap_err();
+    assert_eq!(err.diagnostics().len(), 1);
+    let err = context.wat2wasm("(module (").unwrap_err();
+    assert!(err
+        .diagnostics()
+        .iter()
+        .all(|d| !d.message.contains("i32.add")));
+
+    // Modules parsed with the context aren't affected by later conversions.
+    let module = context.parse_wat("test.wast", sources[1]).unwrap();
+    context.wat2wasm("(module (").unwrap_err();
     module.validate().unwrap();
+    assert_eq!(
+        module
+            .write_binary(&WriteBinaryOptions::default())
+            .unwrap()
+            .as_ref(),
+        &wat2wasm(sources[1]).unwrap()[..]
+    );
+}
+
+#[test]
+fn wabt_buf_deref() {
+    let buf = Wat2Wasm::new().convert("(module)").unwrap();
+    assert_eq!(buf.len(), 8);
+    assert_eq!(&buf[0..4], &[0, 97, 115, 109]);
+
+    fn takes_slice(bytes: &[u8]) -> usize {
+        bytes.len()
+    }
+    assert_eq!(takes_slice(&buf), 8);
+}
+
+#[test]
+fn wabt_buf_into_vec() {
+    let buf = Wat2Wasm::new().convert("(module)").unwrap();
+    assert_eq!(buf.into_vec(), &[0, 97, 115, 109, 1, 0, 0, 0]);
+
+    let buf = Wat2Wasm::new().convert("(module)").unwrap();
+    let vec: Vec<u8> = buf.into();
+    assert_eq!(vec, &[0, 97, 115, 109, 1, 0, 0, 0]);
+}
+
+#[test]
+fn wabt_buf_write_to() {
+    let buf = Wat2Wasm::new().convert("(module)").unwrap();
+    let mut sink = Vec::new();
+    assert_eq!(buf.write_to(&mut sink).unwrap(), 8);
+    assert_eq!(sink, &[0, 97, 115, 109, 1, 0, 0, 0]);
+}
+
+#[test]
+fn send_and_sync() {
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+
+    assert_send::<Features>();
+    assert_sync::<Features>();
+    assert_send::<WabtBuf>();
+    assert_sync::<WabtBuf>();
+    assert_send::<Module>();
+    assert_send::<Error>();
+    assert_sync::<Error>();
+}
+
+#[test]
+fn send_across_threads() {
+    use std::thread;
+
+    let buf = Wat2Wasm::new().convert("(module)").unwrap();
+    let len = thread::spawn(move || buf.as_ref().len()).join().unwrap();
+    assert_eq!(len, 8);
+
+    let module = Module::parse_wat_str("(module (func (export \"f\")))", Features::new()).unwrap();
+    let wasm = thread::spawn(move || {
+        module.validate().unwrap();
+        module.write_binary(&WriteBinaryOptions::default()).unwrap()
+    })
+    .join()
+    .unwrap();
+    assert_eq!(
+        wasm.as_ref(),
+        &wat2wasm("(module (func (export \"f\")))").unwrap()[..]
+    );
+}
+
+#[test]
+fn wat2wasm_convert_into() {
+    let converter = Wat2Wasm::new();
+    let mut out = Vec::new();
+
+    converter
+        .convert_into("(module (func (export \"f\")))", &mut out)
+        .unwrap();
+    assert_eq!(out, wat2wasm("(module (func (export \"f\")))").unwrap());
+
+    converter.convert_into("(module)", &mut out).unwrap();
+    assert_eq!(out, &[0, 97, 115, 109, 1, 0, 0, 0]);
+
+    assert!(converter.convert_into("(module", &mut out).is_err());
+    assert_eq!(out, &[0, 97, 115, 109, 1, 0, 0, 0]);
+}
+
+#[test]
+fn test_wasm2wat_with_options() {
+    let wasm = wat2wasm(
+        r#"(module (func (export "add") (param i32 i32) (result i32) local.get 0 local.get 1 i32.add))"#,
+    )
+    .unwrap();
+
+    let unfolded = wasm2wat_with_options(&wasm, &WriteTextOptions::default()).unwrap();
+    assert_eq!(unfolded, wasm2wat(&wasm).unwrap());
+    assert!(unfolded.contains("local.get 0\n"), "{}", unfolded);
+    assert!(
+        unfolded.contains("(export \"add\" (func 0))"),
+        "{}",
+        unfolded
+    );
+
+    let mut options = WriteTextOptions::default();
+    options.fold_exprs(true).inline_export(true);
+    let folded = wasm2wat_with_options(&wasm, &options).unwrap();
+    assert!(folded.contains("(i32.add\n"), "{}", folded);
+    assert!(folded.contains("(local.get 0)"), "{}", folded);
+    assert!(folded.contains("(export \"add\")"), "{}", folded);
+    assert_eq!(
+        folded,
+        Wasm2Wat::new()
+            .fold_exprs(true)
+            .inline_export(true)
+            .convert(&wasm)
+            .map(|text| String::from_utf8(text.into_vec()).unwrap())
+            .unwrap()
+    );
+}
+
+#[test]
+fn reuse_write_options() {
+    let mut binary_options = WriteBinaryOptions::default();
+    binary_options
+        .canonicalize_lebs(false)
+        .name_section(NameSectionLevel::Full);
+    let source = "(module (func $f))";
+    let wasm = Wat2Wasm::new()
+        .write_binary_options(binary_options.clone())
+        .convert(source)
+        .unwrap();
+    let expected = Wat2Wasm::new()
+        .canonicalize_lebs(false)
+        .write_debug_names(true)
+        .convert(source)
+        .unwrap();
+    assert_eq!(wasm.as_ref(), expected.as_ref());
+
+    let mut text_options = WriteTextOptions::default();
+    text_options.fold_exprs(true).inline_export(true);
+    let wasm = wat2wasm(r#"(module (func (export "f") (result i32) i32.const 1))"#).unwrap();
+    for _ in 0..2 {
+        let text = Wasm2Wat::new()
+            .write_text_options(text_options.clone())
+            .convert(&wasm)
+            .unwrap();
+        let text = str::from_utf8(text.as_ref()).unwrap();
+        assert!(text.contains("(func (;0;) (export \"f\")"), "{}", text);
+        assert!(text.contains("(i32.const 1)"), "{}", text);
+    }
+}
+
+#[test]
+fn wat2wasm_convert_reader() {
+    use std::io::Cursor;
+
+    let source = "(module (func (export \"f\") (result i32) i32.const 42))";
+    let wasm = Wat2Wasm::new()
+        .convert_reader(Cursor::new(source.as_bytes()))
+        .unwrap();
+    assert_eq!(wasm.as_ref(), &wat2wasm(source).unwrap()[..]);
+
+    assert!(Wat2Wasm::new()
+        .convert_reader(Cursor::new(&b"(module"[..]))
+        .is_err());
+
+    struct FailingReader;
+    impl io::Read for FailingReader {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe"))
+        }
+    }
+    match Wat2Wasm::new().convert_reader(FailingReader) {
+        Err(err) => {
+            match *err.kind() {
+                ErrorKind::Io(ref io_err) => assert_eq!(io_err.kind(), io::ErrorKind::BrokenPipe),
+                ref kind => panic!("unexpected error kind: {:?}", kind),
+            }
+            assert_eq!(err.to_string(), "failed to read source: broken pipe");
+            assert!(error::Error::source(&err).is_some());
+        }
+        Ok(_) => panic!("expected an error"),
+    }
+}
+
+#[test]
+fn test_wat2wasm_batch() {
+    let sources: Vec<String> = (0..100)
+        .map(|i| {
+            if i % 7 == 3 {
+                "(module (func (result i32)))".to_owned()
+            } else {
+                format!("(module (func (result i32) i32.const {}))", i)
+            }
+        })
+        .collect();
+    let results = wat2wasm_batch(sources.clone());
+    assert_eq!(results.len(), sources.len());
+    for (source, result) in sources.iter().zip(results) {
+        match wat2wasm(source) {
+            Ok(expected) => assert_eq!(result.unwrap(), expected),
+            Err(_) => assert!(result.is_err()),
+        }
+    }
+
+    assert!(wat2wasm_batch(Vec::new()).is_empty());
+}
+
+#[test]
+fn wat2wasm_features() {
+    let source = "(module (func $f return_call $f))";
+    assert!(Wat2Wasm::new().convert(source).is_err());
+
+    let mut features = Features::new();
+    features.enable_tail_call();
+    assert!(Wat2Wasm::new().features(features).convert(source).is_ok());
+
+    let mut wat2wasm = Wat2Wasm::new();
+    wat2wasm.features_mut().enable_tail_call();
+    assert!(wat2wasm.convert(source).is_ok());
+}
+
+#[test]
+fn wat2wasm_log() {
+    let source = r#"
+(module
+  (func (export "add") (param i32 i32) (result i32)
+    local.get 0
+    local.get 1
+    i32.add))
+"#;
+
+    let (binary, log) = Wat2Wasm::new().convert_with_log(source).unwrap();
+    assert_eq!(binary.as_ref(), &wat2wasm(source).unwrap()[..]);
+    assert!(log.is_empty());
+
+    let (binary, log) = Wat2Wasm::new().log(true).convert_with_log(source).unwrap();
+    assert_eq!(binary.as_ref(), &wat2wasm(source).unwrap()[..]);
+    assert!(!log.is_empty());
 }
 
 #[test]
@@ -1368,7 +5409,7 @@ fn test_wat2wasm() {
 
     assert_eq!(
         wat2wasm("(modu"),
-        Err(Error(ErrorKind::Parse(
+        Err(Error::from(ErrorKind::Parse(
             r#"test.wast:1:2: error: unexpected token "modu", expected a module field or a module.
 (modu
  ^^^^
@@ -1378,6 +5419,406 @@ fn test_wat2wasm() {
     );
 }
 
+#[test]
+fn error_message() {
+    let err = wat2wasm("(modu").unwrap_err();
+    assert!(err
+        .message()
+        .unwrap()
+        .starts_with("test.wast:1:2: error: unexpected token"));
+
+    assert_eq!(Error::from(ErrorKind::WriteBinary).message(), None);
+}
+
+#[test]
+fn error_display() {
+    let err = wat2wasm("(modu").unwrap_err();
+    let displayed = err.to_string();
+    assert!(displayed.contains("unexpected token \"modu\""));
+    assert!(displayed.contains("\n(modu\n"));
+
+    assert_eq!(
+        Error::from(ErrorKind::WriteBinary).to_string(),
+        "failed to write binary"
+    );
+}
+
+#[test]
+fn error_diagnostics() {
+    let err = wat2wasm(
+        r#"(module
+  (func (result i32))
+  (func (result i64)
+    i32.const 0))
+"#,
+    )
+    .unwrap_err();
+    let diagnostics = err.diagnostics();
+    assert_eq!(diagnostics.len(), 2);
+    assert!(diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic.line.is_some() && diagnostic.offset.is_none()));
+    assert!(diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic.message.contains("type mismatch")));
+    assert!(diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic.severity == Severity::Error));
+
+    let err = wasm2wat([0, 97, 115, 109]).unwrap_err();
+    assert_eq!(err.diagnostics().len(), 1);
+    assert_eq!(err.diagnostics()[0].offset, Some(4));
+}
+
+#[test]
+fn error_source() {
+    use std::error::Error as _;
+
+    let err = Module::parse_wat("test\0.wast", "(module)", Features::new())
+        .err()
+        .unwrap();
+    match *err.kind() {
+        ErrorKind::Nul(ref nul_err) => assert_eq!(nul_err.nul_position(), 4),
+        ref other => panic!("unexpected error kind: {:?}", other),
+    }
+    assert!(err.source().is_some());
+
+    let err = wat2wasm("(modu").unwrap_err();
+    assert!(err.source().is_none());
+}
+
+#[test]
+fn parse_error_location() {
+    let err = wat2wasm("(modu").unwrap_err();
+    assert_eq!(
+        err.location(),
+        Some(ParseLocation {
+            filename: "test.wast".to_owned(),
+            line: 1,
+            col: 2,
+        })
+    );
+
+    let err = wasm2wat([0, 97, 115, 109]).unwrap_err();
+    assert_eq!(err.location(), None);
+}
+
+#[test]
+fn wat_macro() {
+    let binary = wat!("(module)");
+    assert_eq!(binary, &[0, 97, 115, 109, 1, 0, 0, 0]);
+}
+
+#[test]
+fn test_wasm2wat_with_features() {
+    let mut features = Features::new();
+    features.enable_tail_call();
+    let binary = wat2wasm_with_features(
+        r#"
+(module
+  (func $f
+    return_call $f))
+"#,
+        features.clone(),
+    )
+    .unwrap();
+
+    assert!(wasm2wat(&binary).is_err());
+    let text = wasm2wat_with_features(&binary, features).unwrap();
+    assert!(text.contains("return_call 0"));
+}
+
+#[test]
+fn wasm2wat_generate_names() {
+    let binary = wat2wasm(
+        r#"
+(module
+  (func $callee)
+  (func
+    call $callee))
+"#,
+    )
+    .unwrap();
+
+    let text = Wasm2Wat::new().convert(&binary).unwrap();
+    assert!(!text.contains(&b'$'));
+
+    let text = Wasm2Wat::new()
+        .generate_names(true)
+        .convert(&binary)
+        .unwrap();
+    let text = str::from_utf8(&text).unwrap();
+    assert!(text.contains("(func $f0"));
+    assert!(text.contains("call $f0"));
+}
+
+#[test]
+fn wasm2wat_explicit_indices() {
+    let binary = Wat2Wasm::new()
+        .write_debug_names(true)
+        .convert(
+            r#"
+(module
+  (import "env" "imported" (func $imported))
+  (type $void (func))
+  (global $g (mut i32) (i32.const 0))
+  (func $first)
+  (func
+    call $first))
+"#,
+        )
+        .unwrap();
+
+    let text = Wasm2Wat::new()
+        .read_debug_names(true)
+        .convert(&binary)
+        .unwrap();
+    let text = str::from_utf8(&text).unwrap();
+    assert!(text.contains("(func $first (type 0)"));
+
+    let text = Wasm2Wat::new()
+        .read_debug_names(true)
+        .explicit_indices(true)
+        .convert(&binary)
+        .unwrap();
+    let text = str::from_utf8(&text).unwrap();
+    assert!(text.contains("(func $imported (;0;)"));
+    assert!(text.contains("(func $first (;1;)"));
+    assert!(text.contains("(func (;2;)"));
+    assert!(text.contains("(global $g (;0;)"));
+
+    let roundtrip = Wat2Wasm::new()
+        .write_debug_names(true)
+        .convert(text)
+        .unwrap();
+    assert_eq!(binary.as_ref(), roundtrip.as_ref());
+}
+
+#[test]
+fn write_text_explicit_indices_all_kinds() {
+    let mut features = Features::new();
+    features.enable_exceptions();
+    let source = r#"
+(module
+  (import "env" "table" (table $imported_table 1 funcref))
+  (import "env" "memory" (memory $imported_memory 1))
+  (import "env" "global" (global $imported_global i32))
+  (import "env" "tag" (tag $imported_tag (param i32)))
+  (type $void (func))
+  (tag $tag (param i32))
+  (global $global i32 (i32.const 0))
+  (func $func (type $void))
+  (elem $elem (i32.const 0) func $func)
+  (data $data (i32.const 0) "hi"))
+"#;
+    let mut module = Module::parse_wat("test.wast", source, features.clone()).unwrap();
+    module.resolve_names().unwrap();
+
+    let mut options = WriteTextOptions::default();
+    options.explicit_indices(true);
+    let text = module.write_text(&options).unwrap();
+    let text = str::from_utf8(&text).unwrap();
+    for item in &[
+        "(table $imported_table (;0;)",
+        "(memory $imported_memory (;0;)",
+        "(global $imported_global (;0;)",
+        "(tag $imported_tag (;0;)",
+        "(type $void (;0;)",
+        "(tag $tag (;1;)",
+        "(global $global (;1;)",
+        "(func $func (;0;)",
+        "(elem $elem (;0;)",
+        "(data $data (;0;)",
+    ] {
+        assert!(text.contains(item), "{} is missing from:\n{}", item, text);
+    }
+
+    assert_eq!(
+        wat2wasm_with_features(text, features.clone()).unwrap(),
+        wat2wasm_with_features(source, features).unwrap()
+    );
+}
+
+#[test]
+fn test_validate_wasm() {
+    let valid = wat2wasm("(module (func (result i32) i32.const 0))").unwrap();
+    assert_eq!(validate_wasm(&valid, Features::new()), Ok(()));
+
+    // Same module, but the function returns an i64 constant instead of an i32.
+    let mut invalid = valid.clone();
+    let pos = invalid.iter().position(|&b| b == 0x41).unwrap();
+    invalid[pos] = 0x42;
+    let err = validate_wasm(&invalid, Features::new()).unwrap_err();
+    match *err.kind() {
+        ErrorKind::Validate(_) => {}
+        ref kind => panic!("expected validation error, got {:?}", kind),
+    }
+}
+
+#[test]
+fn test_validate_wat() {
+    assert_eq!(
+        validate_wat(
+            "(module (func (param i32) local.get 0 drop))",
+            Features::new()
+        ),
+        Ok(())
+    );
+
+    let err = validate_wat("(module (func i32.add drop))", Features::new()).unwrap_err();
+    match *err.kind() {
+        ErrorKind::Validate(_) => {}
+        ref kind => panic!("expected validation error, got {:?}", kind),
+    }
+}
+
+#[test]
+fn test_wasm_decompile() {
+    let wasm = wat2wasm(
+        r#"
+(module
+  (func (export "max") (param i32 i32) (result i32)
+    local.get 0
+    local.get 1
+    i32.gt_s
+    if (result i32)
+      local.get 0
+    else
+      local.get 1
+    end))
+"#,
+    )
+    .unwrap();
+
+    let text = wasm_decompile(&wasm, Features::new()).unwrap();
+    assert!(text.contains("function max("));
+    assert!(text.contains("if ("));
+    assert!(text.contains("} else {"));
+}
+
+#[test]
+fn test_objdump_headers() {
+    let wasm = wat2wasm(
+        r#"
+(module
+  (func (param i32) (result i32)
+    local.get 0))
+"#,
+    )
+    .unwrap();
+
+    let section = |id: u8, name: &str, offset: usize, size: usize| SectionInfo {
+        id,
+        name: name.to_owned(),
+        offset,
+        size,
+    };
+    assert_eq!(
+        objdump_headers(&wasm),
+        Ok(vec![
+            section(1, "Type", 0x0a, 6),
+            section(3, "Function", 0x12, 2),
+            section(10, "Code", 0x16, 6),
+        ])
+    );
+
+    assert!(objdump_headers([0, 97, 115, 109, 1, 0, 0, 0, 1, 10]).is_err());
+    assert!(objdump_headers(b"\0wat").is_err());
+}
+
+#[test]
+fn wasm2wat_with_offsets() {
+    let binary = wat2wasm(
+        r#"
+(module
+  (import "env" "f" (func $f (param i32)))
+  (func (export "g") (param i32) (result i32)
+    (local i64)
+    block
+      local.get 0
+      br_if 0
+      i32.const 0
+      call $f
+    end
+    local.get 0
+    if (result i32)
+      i32.const 1
+    else
+      i32.const 2
+    end)
+  (func
+    i32.const 0
+    if
+      nop
+    end))
+"#,
+    )
+    .unwrap();
+
+    let text = Wasm2Wat::new().with_offsets(true).convert(&binary).unwrap();
+    let text = str::from_utf8(&text).unwrap();
+
+    let instructions: Vec<(usize, &str)> = text
+        .lines()
+        .filter_map(|line| {
+            let start = line.find("(;@0x")? + "(;@0x".len();
+            let end = start + line[start..].find(";)")?;
+            let offset = usize::from_str_radix(&line[start..end], 16).ok()?;
+            let name = line[end + 2..].split_whitespace().next()?;
+            Some((offset, name.trim_end_matches(')')))
+        })
+        .collect();
+    let names: Vec<&str> = instructions.iter().map(|&(_, name)| name).collect();
+    assert_eq!(
+        names,
+        vec![
+            "block",
+            "local.get",
+            "br_if",
+            "i32.const",
+            "call",
+            "end",
+            "local.get",
+            "if",
+            "i32.const",
+            "else",
+            "i32.const",
+            "end",
+            "i32.const",
+            "if",
+            "nop",
+            "end",
+        ]
+    );
+    assert!(instructions.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    let opcodes: Vec<u8> = instructions
+        .iter()
+        .map(|&(offset, _)| binary[offset])
+        .collect();
+    assert_eq!(
+        opcodes,
+        vec![
+            0x02, 0x20, 0x0d, 0x41, 0x10, 0x0b, 0x20, 0x04, 0x41, 0x05, 0x41, 0x0b, 0x41, 0x04,
+            0x01, 0x0b,
+        ]
+    );
+
+    // The comments don't change the meaning of the text.
+    assert_eq!(wat2wasm(text).unwrap(), binary);
+
+    match Wasm2Wat::new()
+        .fold_exprs(true)
+        .with_offsets(true)
+        .convert(&binary)
+    {
+        Err(err) => match *err.kind() {
+            ErrorKind::WriteOffsets(_) => {}
+            ref kind => panic!("unexpected error kind: {:?}", kind),
+        },
+        Ok(_) => panic!("expected an error"),
+    }
+}
+
 #[test]
 fn test_wasm2wat() {
     assert_eq!(
@@ -1392,7 +5833,7 @@ fn test_wasm2wat() {
         wasm2wat(&[
             0, 97, 115, 109, // \0ASM - magic
         ]),
-        Err(Error(ErrorKind::Deserialize(
+        Err(Error::from(ErrorKind::Deserialize(
             "0000004: error: unable to read uint32_t: version\n".to_owned()
         ))),
     );
@@ -2,7 +2,15 @@
 pub struct RuntimeValue {
     #[serde(rename = "type")]
     pub value_type: String,
-    pub value: String,
+    pub lane_type: Option<String>,
+    pub value: RuntimeValueData,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum RuntimeValueData {
+    Scalar(String),
+    Lanes(Vec<String>),
 }
 
 #[derive(Deserialize, Debug)]
@@ -70,6 +78,8 @@ pub enum Command {
         action: Action,
         text: String,
     },
+    #[serde(rename = "assert_exception")]
+    AssertException { line: u64, action: Action },
     #[serde(rename = "assert_unlinkable")]
     AssertUnlinkable {
         line: u64,
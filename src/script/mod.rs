@@ -7,7 +7,7 @@
 //! # Example
 //!
 //! ```rust
-//! use wabt::script::{ScriptParser, Command, CommandKind, Action, Value};
+//! use wabt::script::{ScriptParser, Command, CommandKind, Action, ExpectedValue, Value};
 //! # use wabt::script::Error;
 //!
 //! # fn try_main() -> Result<(), Error> {
@@ -52,7 +52,7 @@
 //!                     Value::I32(3)
 //!                 ],
 //!             });
-//!             assert_eq!(expected, vec![Value::I32(5)]);
+//!             assert_eq!(expected, vec![ExpectedValue::Value(Value::I32(5))]);
 //!         },
 //!         _ => panic!("there are no other commands apart from that defined above"),
 //!     }
@@ -72,13 +72,19 @@ use std::collections::HashMap;
 use std::error;
 use std::ffi::CString;
 use std::fmt;
+use std::fs;
 use std::io;
+use std::ops::Range;
+use std::path::Path;
 use std::str;
 use std::vec;
 
 use serde_json;
 
-use super::{Error as WabtError, Features, Script, WabtBuf, WabtWriteScriptResult};
+use super::{
+    sexpr_children, Error as WabtError, Features, Script, ValueType, WabtBuf,
+    WabtWriteScriptResultRelease,
+};
 
 mod json;
 
@@ -89,6 +95,13 @@ pub enum Error {
     IoError(io::Error),
     /// WABT reported an error while converting wast to json.
     WabtError(WabtError),
+    /// The json produced by wast2json couldn't be deserialized.
+    Json(serde_json::Error),
+    /// The script file name doesn't have a `.wast` extension.
+    InvalidExtension {
+        /// The file name that was provided.
+        provided: String,
+    },
     /// Other error represented by String.
     Other(String),
     /// Not a different kind of an error but just a wrapper for a error
@@ -105,7 +118,11 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Error::IoError(ref io_err) => write!(f, "IO error: {}", io_err),
-            Error::WabtError(ref wabt_err) => write!(f, "wabt error: {:?}", wabt_err),
+            Error::WabtError(ref wabt_err) => write!(f, "wabt error: {}", wabt_err),
+            Error::Json(ref json_err) => write!(f, "JSON error: {}", json_err),
+            Error::InvalidExtension { ref provided } => {
+                write!(f, "Provided {} should have .wast extension", provided)
+            }
             Error::Other(ref message) => write!(f, "{}", message),
             Error::WithLineInfo { line, ref error } => write!(f, "At line {}: {}", line, error),
         }
@@ -117,7 +134,8 @@ impl error::Error for Error {
         match *self {
             Error::IoError(ref io_err) => Some(io_err),
             Error::WabtError(ref wabt_err) => Some(wabt_err),
-            Error::Other(_) => None,
+            Error::Json(ref json_err) => Some(json_err),
+            Error::InvalidExtension { .. } | Error::Other(_) => None,
             Error::WithLineInfo { ref error, .. } => Some(error),
         }
     }
@@ -135,6 +153,12 @@ impl From<io::Error> for Error {
     }
 }
 
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Error {
+        Error::Json(e)
+    }
+}
+
 /// Bitwise conversion from T
 pub trait FromBits<T> {
     /// Convert `other` to `Self`, preserving bitwise representation
@@ -160,7 +184,13 @@ impl FromBits<u64> for f64 {
 }
 
 /// Wasm value
-#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+///
+/// `Value` is `Eq` and `Hash` if the float types are. This isn't the case for the default
+/// `f32` and `f64`, since `NaN != NaN` and `0.0 == -0.0`. To use values as `HashMap` keys or
+/// collect them into a `HashSet`, parse the script with the raw bits as the float types,
+/// i.e. `ScriptParser<u32, u64>`. Floats then compare bitwise, so NaNs with the same
+/// payload are equal and `-0.0` differs from `0.0`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Serialize, Deserialize)]
 pub enum Value<F32 = f32, F64 = f64> {
     /// 32-bit signed or unsigned integer.
     I32(i32),
@@ -172,6 +202,10 @@ pub enum Value<F32 = f32, F64 = f64> {
     F64(F64),
     /// 128-bit vector.
     V128(u128),
+    /// Function reference, `None` for the null reference.
+    FuncRef(Option<u32>),
+    /// External reference, `None` for the null reference.
+    ExternRef(Option<u32>),
 }
 
 impl<F32: FromBits<u32>, F64: FromBits<u64>> Value<F32, F64> {
@@ -184,8 +218,137 @@ impl<F32: FromBits<u32>, F64: FromBits<u64>> Value<F32, F64> {
     }
 }
 
+impl Value<f32, f64> {
+    /// Format this value as a wast constant expression, e.g. `(i32.const 42)`.
+    ///
+    /// Floats are written as exact hexadecimal literals (NaNs with their payload), so
+    /// parsing the result back yields a bitwise identical value.
+    pub fn to_wast_literal(&self) -> String {
+        match *self {
+            Value::I32(v) => format!("(i32.const {})", v),
+            Value::I64(v) => format!("(i64.const {})", v),
+            Value::F32(v) => format!("(f32.const {})", hex_float(u64::from(v.to_bits()), 8, 23)),
+            Value::F64(v) => format!("(f64.const {})", hex_float(v.to_bits(), 11, 52)),
+            Value::V128(v) => format!(
+                "(v128.const i32x4 0x{:08x} 0x{:08x} 0x{:08x} 0x{:08x})",
+                v as u32,
+                (v >> 32) as u32,
+                (v >> 64) as u32,
+                (v >> 96) as u32
+            ),
+            Value::FuncRef(None) => "(ref.null func)".to_owned(),
+            Value::FuncRef(Some(v)) => format!("(ref.func {})", v),
+            Value::ExternRef(None) => "(ref.null extern)".to_owned(),
+            Value::ExternRef(Some(v)) => format!("(ref.extern {})", v),
+        }
+    }
+}
+
+/// Format the bits of an IEEE 754 float as a wast hexadecimal float literal.
+fn hex_float(bits: u64, exp_bits: u32, mant_bits: u32) -> String {
+    let sign = if bits >> (exp_bits + mant_bits) != 0 {
+        "-"
+    } else {
+        ""
+    };
+    let max_exp = (1 << exp_bits) - 1;
+    let bias = (max_exp >> 1) as i64;
+    let exp = (bits >> mant_bits) & max_exp;
+    let mant = bits & ((1 << mant_bits) - 1);
+
+    if exp == max_exp {
+        return if mant == 0 {
+            format!("{}inf", sign)
+        } else {
+            format!("{}nan:0x{:x}", sign, mant)
+        };
+    }
+    if exp == 0 && mant == 0 {
+        return format!("{}0x0p+0", sign);
+    }
+
+    // Pad the mantissa so that it occupies a whole number of hex digits.
+    let shift = (4 - mant_bits % 4) % 4;
+    let digits = ((mant_bits + shift) / 4) as usize;
+    let frac = format!("{:0width$x}", mant << shift, width = digits);
+    let frac = frac.trim_end_matches('0');
+    let (lead, exp) = if exp == 0 {
+        (0, 1 - bias)
+    } else {
+        (1, exp as i64 - bias)
+    };
+    if frac.is_empty() {
+        format!("{}0x{}p{:+}", sign, lead, exp)
+    } else {
+        format!("{}0x{}.{}p{:+}", sign, lead, frac, exp)
+    }
+}
+
+/// NaN pattern that a float result is expected to match.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Serialize, Deserialize)]
+pub enum NanPattern {
+    /// NaN in canonical form, i.e. with only the MSB of the fraction field set.
+    Canonical,
+    /// NaN with 1 in MSB of fraction field and any other payload.
+    Arithmetic,
+}
+
+/// Value that an action is expected to yield.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Serialize, Deserialize)]
+pub enum ExpectedValue<F32 = f32, F64 = f64> {
+    /// Exactly this value.
+    Value(Value<F32, F64>),
+    /// 32-bit floating point NaN matching the pattern.
+    F32Nan(NanPattern),
+    /// 64-bit floating point NaN matching the pattern.
+    F64Nan(NanPattern),
+}
+
+impl<F32, F64> From<Value<F32, F64>> for ExpectedValue<F32, F64> {
+    fn from(value: Value<F32, F64>) -> ExpectedValue<F32, F64> {
+        ExpectedValue::Value(value)
+    }
+}
+
+/// Check whether a value yielded by an action matches the expected value.
+///
+/// Floats are compared bitwise, so `-0.0` doesn't match `0.0` and an expected NaN only
+/// matches a NaN with the same payload. NaN patterns match as defined by the spec:
+///
+/// - a canonical NaN has only the MSB of the fraction field set,
+/// - an arithmetic NaN has the MSB of the fraction field set and any other payload.
+///
+/// The sign of a NaN is ignored in both cases.
+pub fn matches(expected: &ExpectedValue, actual: &Value) -> bool {
+    const F32_NAN: u32 = 0x7fc0_0000;
+    const F64_NAN: u64 = 0x7ff8_0000_0000_0000;
+
+    match (*expected, *actual) {
+        (ExpectedValue::Value(Value::F32(expected)), Value::F32(actual)) => {
+            expected.to_bits() == actual.to_bits()
+        }
+        (ExpectedValue::Value(Value::F64(expected)), Value::F64(actual)) => {
+            expected.to_bits() == actual.to_bits()
+        }
+        (ExpectedValue::Value(expected), actual) => expected == actual,
+        (ExpectedValue::F32Nan(NanPattern::Canonical), Value::F32(actual)) => {
+            actual.to_bits() & 0x7fff_ffff == F32_NAN
+        }
+        (ExpectedValue::F32Nan(NanPattern::Arithmetic), Value::F32(actual)) => {
+            actual.to_bits() & F32_NAN == F32_NAN
+        }
+        (ExpectedValue::F64Nan(NanPattern::Canonical), Value::F64(actual)) => {
+            actual.to_bits() & 0x7fff_ffff_ffff_ffff == F64_NAN
+        }
+        (ExpectedValue::F64Nan(NanPattern::Arithmetic), Value::F64(actual)) => {
+            actual.to_bits() & F64_NAN == F64_NAN
+        }
+        (ExpectedValue::F32Nan(_), _) | (ExpectedValue::F64Nan(_), _) => false,
+    }
+}
+
 /// Description of action that should be performed on a wasm module.
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum Action<F32 = f32, F64 = f64> {
     /// Invoke a specified function.
     Invoke {
@@ -215,40 +378,125 @@ fn parse_value<F32: FromBits<u32>, F64: FromBits<u64>>(
             .parse()
             .map_err(|_| Error::Other(format!("can't parse '{}' as '{}'", str_val, str_ty)))
     }
-    let value = match test_val.value_type.as_ref() {
-        "i32" => {
-            let unsigned: u32 = parse_val(&test_val.value, &test_val.value_type)?;
+    fn parse_ref(str_val: &str, str_ty: &str) -> Result<Option<u32>, Error> {
+        if str_val == "null" {
+            Ok(None)
+        } else {
+            parse_val(str_val, str_ty).map(Some)
+        }
+    }
+    let str_val = match test_val.value {
+        json::RuntimeValueData::Scalar(ref str_val) => str_val,
+        json::RuntimeValueData::Lanes(ref lanes) if test_val.value_type == "v128" => {
+            return parse_v128_lanes(lanes, test_val.lane_type.as_deref());
+        }
+        json::RuntimeValueData::Lanes(_) => {
+            return Err(Error::Other(format!(
+                "unexpected lanes for type '{}'",
+                test_val.value_type
+            )));
+        }
+    };
+    let value_type: ValueType = test_val
+        .value_type
+        .parse()
+        .map_err(|_| Error::Other(format!("Unknown type '{}'", test_val.value_type)))?;
+    let value = match value_type {
+        ValueType::I32 => {
+            let unsigned: u32 = parse_val(str_val, &test_val.value_type)?;
             Value::I32(unsigned as i32)
         }
-        "i64" => {
-            let unsigned: u64 = parse_val(&test_val.value, &test_val.value_type)?;
+        ValueType::I64 => {
+            let unsigned: u64 = parse_val(str_val, &test_val.value_type)?;
             Value::I64(unsigned as i64)
         }
-        "f32" => {
-            let unsigned: u32 = parse_val(&test_val.value, &test_val.value_type)?;
+        ValueType::F32 => {
+            let unsigned: u32 = parse_val(str_val, &test_val.value_type)?;
             Value::decode_f32(unsigned)
         }
-        "f64" => {
-            let unsigned: u64 = parse_val(&test_val.value, &test_val.value_type)?;
+        ValueType::F64 => {
+            let unsigned: u64 = parse_val(str_val, &test_val.value_type)?;
             Value::decode_f64(unsigned)
         }
-        "v128" => {
-            let unsigned: u128 = parse_val(&test_val.value, &test_val.value_type)?;
+        ValueType::V128 => {
+            let unsigned: u128 = parse_val(str_val, &test_val.value_type)?;
             Value::V128(unsigned)
         }
-        other_ty => {
-            return Err(Error::Other(format!("Unknown type '{}'", other_ty)));
-        }
+        ValueType::FuncRef => Value::FuncRef(parse_ref(str_val, &test_val.value_type)?),
+        ValueType::ExternRef => Value::ExternRef(parse_ref(str_val, &test_val.value_type)?),
     };
     Ok(value)
 }
 
+/// Reassemble a v128 value from the lanes emitted by wast2json, e.g. four `i32` lanes.
+///
+/// Each lane holds the bits of the lane value as an unsigned decimal number, and the first
+/// lane occupies the least significant bits.
+fn parse_v128_lanes<F32, F64>(
+    lanes: &[String],
+    lane_type: Option<&str>,
+) -> Result<Value<F32, F64>, Error> {
+    let lane_type = lane_type.ok_or_else(|| Error::Other("v128 without lane type".to_owned()))?;
+    let lane_bits = match lane_type {
+        "i8" => 8,
+        "i16" => 16,
+        "i32" | "f32" => 32,
+        "i64" | "f64" => 64,
+        other => return Err(Error::Other(format!("Unknown lane type '{}'", other))),
+    };
+    if lanes.len() * lane_bits != 128 {
+        return Err(Error::Other(format!(
+            "expected {} lanes of type '{}', got {}",
+            128 / lane_bits,
+            lane_type,
+            lanes.len()
+        )));
+    }
+
+    let mut bits = 0u128;
+    for (index, lane) in lanes.iter().enumerate() {
+        let lane_val: u64 = lane
+            .parse()
+            .map_err(|_| Error::Other(format!("can't parse '{}' as '{}'", lane, lane_type)))?;
+        if lane_bits < 64 && lane_val >> lane_bits != 0 {
+            return Err(Error::Other(format!(
+                "lane value '{}' doesn't fit '{}'",
+                lane, lane_type
+            )));
+        }
+        bits |= u128::from(lane_val) << (index * lane_bits);
+    }
+    Ok(Value::V128(bits))
+}
+
 fn parse_value_list<F32: FromBits<u32>, F64: FromBits<u64>>(
     test_vals: &[json::RuntimeValue],
 ) -> Result<Vec<Value<F32, F64>>, Error> {
     test_vals.iter().map(parse_value).collect()
 }
 
+fn parse_expected<F32: FromBits<u32>, F64: FromBits<u64>>(
+    test_val: &json::RuntimeValue,
+) -> Result<ExpectedValue<F32, F64>, Error> {
+    let pattern = match test_val.value {
+        json::RuntimeValueData::Scalar(ref str_val) if str_val == "nan:canonical" => {
+            NanPattern::Canonical
+        }
+        json::RuntimeValueData::Scalar(ref str_val) if str_val == "nan:arithmetic" => {
+            NanPattern::Arithmetic
+        }
+        _ => return parse_value(test_val).map(ExpectedValue::Value),
+    };
+    match test_val.value_type.as_ref() {
+        "f32" => Ok(ExpectedValue::F32Nan(pattern)),
+        "f64" => Ok(ExpectedValue::F64Nan(pattern)),
+        other_ty => Err(Error::Other(format!(
+            "NaN pattern for non-float type '{}'",
+            other_ty
+        ))),
+    }
+}
+
 fn parse_action<F32: FromBits<u32>, F64: FromBits<u64>>(
     test_action: &json::Action,
 ) -> Result<Action<F32, F64>, Error> {
@@ -273,20 +521,81 @@ fn parse_action<F32: FromBits<u32>, F64: FromBits<u64>>(
     Ok(action)
 }
 
-fn wast2json(
+/// Output of [`wast2json`].
+///
+/// [`wast2json`]: fn.wast2json.html
+pub struct Wast2JsonOutput {
+    /// JSON spec of the script, in the format of wabt's `wast2json` tool.
+    pub json: WabtBuf,
+    /// Files of the modules that the JSON spec refers to, keyed by their file name, such
+    /// as `test.0.wasm`.
+    pub modules: HashMap<String, WabtBuf>,
+}
+
+// The buffers can be large, so only their lengths are shown.
+impl fmt::Debug for Wast2JsonOutput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let modules: HashMap<&str, usize> = self
+            .modules
+            .iter()
+            .map(|(name, module)| (name.as_str(), module.as_ref().len()))
+            .collect();
+        f.debug_struct("Wast2JsonOutput")
+            .field("json", &self.json.as_ref().len())
+            .field("modules", &modules)
+            .finish()
+    }
+}
+
+/// Convert a script into a JSON spec and the binaries of its modules, in memory.
+///
+/// This mirrors wabt's `wast2json` tool: the file names of the modules are derived from
+/// `name`, e.g. `test.0.wasm` for `test.wast`. Unlike [`ScriptParser`], `name` can have
+/// any extension.
+///
+/// [`ScriptParser`]: struct.ScriptParser.html
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate wabt;
+/// use wabt::Features;
+/// use wabt::script::wast2json;
+///
+/// fn main() {
+///     let output = wast2json(b"(module)", "test.wast", Features::new()).unwrap();
+///     assert!(output.modules.contains_key("test.0.wasm"));
+/// }
+/// ```
+pub fn wast2json(source: &[u8], name: &str, features: Features) -> Result<Wast2JsonOutput, Error> {
+    let results = write_script(source, name, features)?;
+    let modules = results
+        .module_output_buffers
+        .into_iter()
+        .map(|(name, module)| (name.to_string_lossy().into_owned(), module))
+        .collect();
+    Ok(Wast2JsonOutput {
+        json: results.json_output_buffer,
+        modules,
+    })
+}
+
+fn write_script(
     source: &[u8],
     test_filename: &str,
     features: Features,
-) -> Result<WabtWriteScriptResult, Error> {
+) -> Result<WabtWriteScriptResultRelease, Error> {
     let script = Script::parse(test_filename, source, features.clone())?;
     script.resolve_names()?;
     script.validate()?;
-    let result = script.write_binaries(test_filename)?;
-    Ok(result)
+    script
+        .write_binaries(test_filename)?
+        .take_all()
+        .map_err(|()| Error::Other("Failed to release the wast2json output".to_owned()))
 }
 
 /// This is a handle to get the binary representation of the module.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ModuleBinary {
     module: Vec<u8>,
 }
@@ -310,7 +619,7 @@ impl ModuleBinary {
 }
 
 /// Script's command.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum CommandKind<F32 = f32, F64 = f64> {
     /// Define, validate and instantiate a module.
     Module {
@@ -325,7 +634,7 @@ pub enum CommandKind<F32 = f32, F64 = f64> {
         /// Action to perform.
         action: Action<F32, F64>,
         /// Values that expected to be yielded by the action.
-        expected: Vec<Value<F32, F64>>,
+        expected: Vec<ExpectedValue<F32, F64>>,
     },
     /// Assert that specified action should yield NaN in canonical form.
     AssertReturnCanonicalNan {
@@ -372,6 +681,11 @@ pub enum CommandKind<F32 = f32, F64 = f64> {
         /// Expected failure should be with this message.
         message: String,
     },
+    /// Assert that performing specified action must throw an exception.
+    AssertException {
+        /// Action to perform.
+        action: Action<F32, F64>,
+    },
     /// Assert that specified module fails to link.
     AssertUnlinkable {
         /// Module that should be unlinkable.
@@ -401,19 +715,30 @@ pub enum CommandKind<F32 = f32, F64 = f64> {
 /// It consists of line number and [`CommandKind`].
 ///
 /// [`CommandKind`]: enum.CommandKind.html
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Command<F32 = f32, F64 = f64> {
     /// Line number the command is defined on.
     pub line: u64,
 
+    /// Byte range of the command in the script source, e.g. the whole `(assert_return ...)`
+    /// form.
+    ///
+    /// `None` if the command couldn't be located in the source.
+    pub text_range: Option<Range<usize>>,
+
     /// Kind of the command.
     pub kind: CommandKind<F32, F64>,
 }
 
 /// Parser which allows to parse WebAssembly script text format.
 pub struct ScriptParser<F32 = f32, F64 = f64> {
+    json_output: WabtBuf,
     cmd_iter: vec::IntoIter<json::Command>,
     modules: HashMap<CString, WabtBuf>,
+    last_module: Option<ModuleBinary>,
+    named_modules: HashMap<String, ModuleBinary>,
+    line_starts: Vec<usize>,
+    forms: vec::IntoIter<Range<usize>>,
     _phantom: ::std::marker::PhantomData<(F32, F64)>,
 }
 
@@ -439,37 +764,134 @@ impl<F32: FromBits<u32>, F64: FromBits<u64>> ScriptParser<F32, F64> {
         features: Features,
     ) -> Result<Self, Error> {
         if !test_filename.ends_with(".wast") {
-            return Err(Error::Other(format!(
-                "Provided {} should have .wast extension",
-                test_filename
-            )));
+            return Err(Error::InvalidExtension {
+                provided: test_filename.to_owned(),
+            });
         }
+        ScriptParser::from_source_and_name_unchecked_with_features(source, test_filename, features)
+    }
 
+    /// Create `ScriptParser` from the script in specified file, whatever the extension of
+    /// `test_filename` is.
+    ///
+    /// The `source` should contain valid wast. This is handy for scripts embedded in other
+    /// files or stored under generated names.
+    pub fn from_source_and_name_unchecked(
+        source: &[u8],
+        test_filename: &str,
+    ) -> Result<Self, Error> {
+        ScriptParser::from_source_and_name_unchecked_with_features(
+            source,
+            test_filename,
+            Features::new(),
+        )
+    }
+
+    /// Create `ScriptParser` from the script in specified file, whatever the extension of
+    /// `test_filename` is, parsing with the given features.
+    ///
+    /// The `source` should contain valid wast.
+    pub fn from_source_and_name_unchecked_with_features(
+        source: &[u8],
+        test_filename: &str,
+        features: Features,
+    ) -> Result<Self, Error> {
         // Convert wasm script into json spec and binaries. The output artifacts
         // will be placed in result.
 
-        let results = wast2json(source, test_filename, features.clone())?;
-        let results = results.take_all().expect("Failed to release");
-
-        let json_str = results.json_output_buffer.as_ref();
-
-        let spec: json::Spec =
-            serde_json::from_slice(json_str).expect("Failed to deserialize JSON buffer");
+        let results = write_script(source, test_filename, features.clone())?;
 
-        let json::Spec { commands, .. } = spec;
+        let json::Spec { commands, .. } = parse_json_spec(&results.json_output_buffer)?;
 
         Ok(ScriptParser {
+            json_output: results.json_output_buffer,
             cmd_iter: commands.into_iter(),
             modules: results.module_output_buffers,
+            last_module: None,
+            named_modules: HashMap::new(),
+            line_starts: line_starts(source),
+            forms: top_level_forms(source).into_iter(),
             _phantom: Default::default(),
         })
     }
 
+    /// Create `ScriptParser` from the script in the file at `path`.
+    ///
+    /// The file name is used as the name of the test, so it must have a `.wast` extension.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let test_filename = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| Error::Other(format!("Invalid file name {}", path.display())))?;
+        let source = fs::read(path)?;
+        ScriptParser::from_source_and_name(&source, test_filename)
+    }
+
     /// Create `ScriptParser` from the script source.
     pub fn from_str(source: &str) -> Result<Self, Error> {
         ScriptParser::from_source_and_name(source.as_bytes(), "test.wast")
     }
 
+    /// Find the source range of the next command, which is defined on `line`.
+    ///
+    /// Commands are in the source order, so this is the first form that wasn't matched to
+    /// a previous command and doesn't end before the line.
+    fn text_range(&mut self, line: u64) -> Option<Range<usize>> {
+        let line_start = *self.line_starts.get((line as usize).checked_sub(1)?)?;
+        self.forms.by_ref().find(|form| form.end > line_start)
+    }
+
+    /// Returns the number of commands that haven't been returned by [`next`] yet.
+    ///
+    /// [`next`]: #method.next
+    pub fn len(&self) -> usize {
+        self.cmd_iter.len()
+    }
+
+    /// Returns `true` if all commands were returned by [`next`].
+    ///
+    /// [`next`]: #method.next
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the JSON spec that `wast2json` produced for the script.
+    ///
+    /// This is the whole spec, including the commands that were already returned by
+    /// [`next`]. Module binaries are referenced by their file names.
+    ///
+    /// The parser only keeps the raw output of `wast2json`, so the spec is parsed on every
+    /// call.
+    ///
+    /// [`next`]: #method.next
+    pub fn json_spec(&self) -> Result<serde_json::Value, Error> {
+        Ok(serde_json::from_slice(&self.json_output)?)
+    }
+
+    /// Returns the binary of the module an [`Action`] refers to.
+    ///
+    /// `name` is the `module` field of the action. Modules are resolved against the
+    /// [`Module`] commands returned by [`next`] so far:
+    ///
+    /// - `None` refers to the most recently defined module, named or not.
+    /// - `Some(name)` refers to the most recently defined module declared with that name,
+    ///   such as `$m` for `(module $m ...)`. Names given by [`Register`] are names for
+    ///   imports and aren't considered.
+    ///
+    /// Returns `None` if no such module has been defined yet.
+    ///
+    /// [`Action`]: enum.Action.html
+    /// [`Module`]: enum.CommandKind.html#variant.Module
+    /// [`Register`]: enum.CommandKind.html#variant.Register
+    /// [`next`]: #method.next
+    pub fn module(&self, name: Option<&str>) -> Option<&ModuleBinary> {
+        match name {
+            Some(name) => self.named_modules.get(name),
+            None => self.last_module.as_ref(),
+        }
+    }
+
     /// Returns the next [`Command`] from the script.
     ///
     /// Returns `Err` if an error occurred while parsing the script,
@@ -495,13 +917,14 @@ impl<F32: FromBits<u32>, F64: FromBits<u64>> ScriptParser<F32, F64> {
                 line,
                 name,
                 filename,
-            } => (
-                line,
-                CommandKind::Module {
-                    module: get_module(filename, self),
-                    name,
-                },
-            ),
+            } => {
+                let module = get_module(filename, self);
+                if let Some(ref name) = name {
+                    self.named_modules.insert(name.clone(), module.clone());
+                }
+                self.last_module = Some(module.clone());
+                (line, CommandKind::Module { module, name })
+            }
             json::Command::AssertReturn {
                 line,
                 action,
@@ -510,7 +933,10 @@ impl<F32: FromBits<u32>, F64: FromBits<u64>> ScriptParser<F32, F64> {
                 line,
                 CommandKind::AssertReturn {
                     action: parse_action(&action)?,
-                    expected: parse_value_list(&expected)?,
+                    expected: expected
+                        .iter()
+                        .map(parse_expected)
+                        .collect::<Result<_, _>>()?,
                 },
             ),
             json::Command::AssertReturnCanonicalNan { line, action } => (
@@ -532,6 +958,12 @@ impl<F32: FromBits<u32>, F64: FromBits<u64>> ScriptParser<F32, F64> {
                     message: text,
                 },
             ),
+            json::Command::AssertException { line, action } => (
+                line,
+                CommandKind::AssertException {
+                    action: parse_action(&action)?,
+                },
+            ),
             json::Command::AssertTrap { line, action, text } => (
                 line,
                 CommandKind::AssertTrap {
@@ -593,10 +1025,44 @@ impl<F32: FromBits<u32>, F64: FromBits<u64>> ScriptParser<F32, F64> {
             }
         };
 
-        Ok(Some(Command { line, kind }))
+        let text_range = self.text_range(line);
+        Ok(Some(Command {
+            line,
+            text_range,
+            kind,
+        }))
     }
 }
 
+fn parse_json_spec(json_str: &[u8]) -> Result<json::Spec, Error> {
+    Ok(serde_json::from_slice(json_str)?)
+}
+
+/// Byte offsets of the start of every line in `source`.
+fn line_starts(source: &[u8]) -> Vec<usize> {
+    let newlines = source
+        .iter()
+        .enumerate()
+        .filter(|&(_, &byte)| byte == b'\n')
+        .map(|(offset, _)| offset + 1);
+    Some(0).into_iter().chain(newlines).collect()
+}
+
+/// Byte ranges of the top-level s-expressions in `source`.
+fn top_level_forms(source: &[u8]) -> Vec<Range<usize>> {
+    let text = match str::from_utf8(source) {
+        Ok(text) => text,
+        Err(_) => return Vec::new(),
+    };
+    sexpr_children(text)
+        .into_iter()
+        .map(|form| {
+            let start = form.as_ptr() as usize - text.as_ptr() as usize;
+            start..start + form.len()
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -616,6 +1082,7 @@ mod tests {
             script.next().unwrap().unwrap(),
             Command {
                 line: 2,
+                text_range: Some(1..44),
                 kind: CommandKind::Module {
                     module: ModuleBinary::from_vec(
                         wat2wasm(r#"(func (export "foo") call 0)"#).unwrap()
@@ -628,6 +1095,7 @@ mod tests {
             script.next().unwrap().unwrap(),
             Command {
                 line: 6,
+                text_range: Some(46..95),
                 kind: CommandKind::AssertExhaustion {
                     action: Action::Invoke {
                         module: None,
@@ -640,6 +1108,435 @@ mod tests {
         );
     }
 
+    #[test]
+    fn wast_literal_roundtrip() {
+        let values = vec![
+            Value::I32(-42),
+            Value::I64(i64::MIN),
+            Value::F32(10.5),
+            Value::F32(-f32::from_bits(1)),
+            Value::F32(f32::from_bits(0x7fc0_0123)),
+            Value::F64(0.1),
+            Value::F64(f64::INFINITY),
+            Value::F64(f64::from_bits(0x000f_ffff_ffff_ffff)),
+        ];
+
+        for value in values {
+            let literal = value.to_wast_literal();
+            let ty = &literal[1..4];
+            let wast = format!(
+                r#"
+                (module (func (export "f") (result {ty}) {lit}))
+                (assert_return (invoke "f") {lit})
+                "#,
+                ty = ty,
+                lit = literal,
+            );
+
+            let mut parser: ScriptParser = ScriptParser::from_str(&wast).unwrap();
+            parser.next().unwrap().unwrap();
+            match parser.next().unwrap().unwrap().kind {
+                CommandKind::AssertReturn { expected, .. } => match (&expected[..], value) {
+                    (&[ExpectedValue::Value(Value::F32(actual))], Value::F32(value)) => {
+                        assert_eq!(actual.to_bits(), value.to_bits(), "{}", literal)
+                    }
+                    (&[ExpectedValue::Value(Value::F64(actual))], Value::F64(value)) => {
+                        assert_eq!(actual.to_bits(), value.to_bits(), "{}", literal)
+                    }
+                    (actual, value) => assert_eq!(actual, &[value.into()], "{}", literal),
+                },
+                other => panic!("unexpected command: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn assert_return_v128() {
+        let wast = r#"
+(module
+  (func (export "splat") (param i32) (result v128)
+    local.get 0
+    i32x4.splat))
+
+(assert_return (invoke "splat" (i32.const 7))
+  (v128.const i32x4 7 7 7 7))
+(assert_return (invoke "splat" (i32.const -1))
+  (v128.const i8x16 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1))
+"#;
+        let mut parser: ScriptParser = ScriptParser::from_str(wast).unwrap();
+        parser.next().unwrap().unwrap();
+
+        let expected = |parser: &mut ScriptParser| match parser.next().unwrap().unwrap().kind {
+            CommandKind::AssertReturn { expected, .. } => expected,
+            other => panic!("unexpected command: {:?}", other),
+        };
+        assert_eq!(
+            expected(&mut parser),
+            vec![ExpectedValue::Value(Value::V128(
+                0x0000_0007_0000_0007_0000_0007_0000_0007
+            ))]
+        );
+        assert_eq!(
+            expected(&mut parser),
+            vec![ExpectedValue::Value(Value::V128(u128::MAX))]
+        );
+    }
+
+    #[test]
+    fn assert_return_refs() {
+        let wast = r#"
+(module
+  (func (export "id") (param externref) (result externref)
+    local.get 0)
+  (func (export "null") (result funcref)
+    ref.null func))
+
+(assert_return (invoke "id" (ref.extern 1)) (ref.extern 1))
+(assert_return (invoke "id" (ref.null extern)) (ref.null extern))
+(assert_return (invoke "null") (ref.null func))
+"#;
+        let mut parser: ScriptParser = ScriptParser::from_str(wast).unwrap();
+        parser.next().unwrap().unwrap();
+
+        let mut assert_return = || match parser.next().unwrap().unwrap().kind {
+            CommandKind::AssertReturn {
+                action: Action::Invoke { args, .. },
+                expected,
+            } => (args, expected),
+            other => panic!("unexpected command: {:?}", other),
+        };
+        assert_eq!(
+            assert_return(),
+            (
+                vec![Value::ExternRef(Some(1))],
+                vec![Value::ExternRef(Some(1)).into()]
+            )
+        );
+        assert_eq!(
+            assert_return(),
+            (
+                vec![Value::ExternRef(None)],
+                vec![Value::ExternRef(None).into()]
+            )
+        );
+        assert_eq!(assert_return(), (vec![], vec![Value::FuncRef(None).into()]));
+    }
+
+    #[test]
+    fn assert_return_nan_patterns() {
+        let wast = r#"
+(module
+  (func (export "nans") (result i32 f32 f64)
+    i32.const 1
+    f32.const nan
+    f64.const nan:0x8000000000001))
+
+(assert_return (invoke "nans")
+  (i32.const 1) (f32.const nan:canonical) (f64.const nan:arithmetic))
+"#;
+        let mut parser: ScriptParser = ScriptParser::from_str(wast).unwrap();
+        parser.next().unwrap().unwrap();
+        match parser.next().unwrap().unwrap().kind {
+            CommandKind::AssertReturn { expected, .. } => assert_eq!(
+                expected,
+                vec![
+                    ExpectedValue::Value(Value::I32(1)),
+                    ExpectedValue::F32Nan(NanPattern::Canonical),
+                    ExpectedValue::F64Nan(NanPattern::Arithmetic),
+                ]
+            ),
+            other => panic!("unexpected command: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn resolve_named_modules() {
+        let wast = r#"
+(module $a
+  (func (export "f") (result i32) i32.const 1))
+(module $b
+  (func (export "f") (result i32) i32.const 2))
+
+(assert_return (invoke $a "f") (i32.const 1))
+(assert_return (invoke "f") (i32.const 2))
+"#;
+        let mut parser: ScriptParser = ScriptParser::from_str(wast).unwrap();
+        assert_eq!(parser.module(None), None);
+
+        let mut defined = Vec::new();
+        for _ in 0..2 {
+            match parser.next().unwrap().unwrap().kind {
+                CommandKind::Module { module, name } => defined.push((name.unwrap(), module)),
+                other => panic!("unexpected command: {:?}", other),
+            }
+        }
+        assert_eq!(defined[0].0, "$a");
+        assert_eq!(defined[1].0, "$b");
+
+        for expected_module in &[&defined[0].1, &defined[1].1] {
+            match parser.next().unwrap().unwrap().kind {
+                CommandKind::AssertReturn {
+                    action: Action::Invoke { module, .. },
+                    ..
+                } => assert_eq!(parser.module(module.as_deref()), Some(*expected_module)),
+                other => panic!("unexpected command: {:?}", other),
+            }
+        }
+        assert_eq!(parser.module(Some("$b")), Some(&defined[1].1));
+        assert_eq!(parser.module(Some("$c")), None);
+    }
+
+    #[test]
+    fn json_spec() {
+        let wast = r#"
+(module (func (export "f")))
+(invoke "f")
+"#;
+        let parser: ScriptParser = ScriptParser::from_str(wast).unwrap();
+        let spec = parser.json_spec().unwrap();
+        assert_eq!(spec["source_filename"], "test.wast");
+        assert_eq!(spec["commands"][0]["type"], "module");
+        assert_eq!(spec["commands"][1]["type"], "action");
+        assert_eq!(spec["commands"][1]["action"]["field"], "f");
+    }
+
+    #[test]
+    fn len() {
+        let wast = r#"
+(module (func (export "f")))
+(invoke "f")
+(assert_return (invoke "f"))
+"#;
+        let mut parser: ScriptParser = ScriptParser::from_str(wast).unwrap();
+        for remaining in (0..3).rev() {
+            parser.next().unwrap().unwrap();
+            assert_eq!(parser.len(), remaining);
+        }
+        assert!(parser.is_empty());
+        assert_eq!(parser.next().unwrap(), None);
+    }
+
+    #[test]
+    fn invalid_extension() {
+        let result = ScriptParser::<f32, f64>::from_source_and_name(b"(module)", "test.wat");
+        match result {
+            Err(Error::InvalidExtension { ref provided }) => assert_eq!(provided, "test.wat"),
+            Err(other) => panic!("unexpected error: {:?}", other),
+            Ok(_) => panic!("expected an error"),
+        }
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "Provided test.wat should have .wast extension"
+        );
+    }
+
+    #[test]
+    fn unchecked_file_name() {
+        let wast = r#"
+(module (func (export "f") (result i32) i32.const 42))
+(assert_return (invoke "f") (i32.const 42))
+"#;
+        let mut parser: ScriptParser =
+            ScriptParser::from_source_and_name_unchecked(wast.as_bytes(), "snippet.wat").unwrap();
+        assert_eq!(
+            parser.json_spec().unwrap()["source_filename"],
+            "snippet.wat"
+        );
+        match parser.next().unwrap().unwrap().kind {
+            CommandKind::Module { module, .. } => {
+                assert_eq!(
+                    module.into_vec(),
+                    wat2wasm(r#"(module (func (export "f") (result i32) i32.const 42))"#).unwrap()
+                );
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+        match parser.next().unwrap().unwrap().kind {
+            CommandKind::AssertReturn { expected, .. } => {
+                assert_eq!(expected, vec![ExpectedValue::Value(Value::I32(42))]);
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn wast2json_output() {
+        let wast = r#"
+(module $m (func (export "f") (result i32) i32.const 42))
+(assert_return (invoke "f") (i32.const 42))
+(module (memory 1))
+"#;
+        let output = wast2json(wast.as_bytes(), "test.wast", Features::new()).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(output.json.as_ref()).unwrap();
+        let commands = json["commands"].as_array().unwrap();
+        assert_eq!(commands.len(), 3);
+        assert_eq!(commands[0]["type"], "module");
+        assert_eq!(commands[0]["filename"], "test.0.wasm");
+        assert_eq!(commands[1]["type"], "assert_return");
+
+        let mut names: Vec<_> = output.modules.keys().cloned().collect();
+        names.sort();
+        assert_eq!(names, ["test.0.wasm", "test.1.wasm"]);
+        assert_eq!(
+            output.modules["test.1.wasm"].as_ref(),
+            &wat2wasm("(module (memory 1))").unwrap()[..]
+        );
+
+        assert!(wast2json(b"(module", "test.wast", Features::new()).is_err());
+
+        let output = wast2json(b"(module)", "test.wast", Features::new()).unwrap();
+        let debug = format!("{:?}", output);
+        assert!(debug.starts_with("Wast2JsonOutput { json: "), "{}", debug);
+        assert!(
+            debug.ends_with(r#"modules: {"test.0.wasm": 8} }"#),
+            "{}",
+            debug
+        );
+    }
+
+    #[test]
+    fn from_file() {
+        let dir = std::env::temp_dir().join(format!("wabt-from-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("test.wast");
+        fs::write(&path, r#"(module (func (export "f")))"#).unwrap();
+        let mut parser: ScriptParser = ScriptParser::from_file(&path).unwrap();
+        match parser.next().unwrap().unwrap().kind {
+            CommandKind::Module { name: None, .. } => {}
+            other => panic!("unexpected command: {:?}", other),
+        }
+
+        let wrong_extension = dir.join("test.wat");
+        fs::write(&wrong_extension, "(module)").unwrap();
+        assert!(ScriptParser::<f32, f64>::from_file(&wrong_extension).is_err());
+
+        match ScriptParser::<f32, f64>::from_file(dir.join("missing.wast")) {
+            Err(Error::IoError(_)) => {}
+            Err(other) => panic!("unexpected error: {:?}", other),
+            Ok(_) => panic!("expected an error"),
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn text_range() {
+        let wast = r#"
+(module $m (func (export "f") (result i32)
+  ;; A comment with a parenthesis (.
+  i32.const 1))
+(assert_return (invoke "f") (i32.const 1)) (invoke "f")
+(register "m" $m)
+"#;
+        let mut parser: ScriptParser = ScriptParser::from_str(wast).unwrap();
+        let mut texts = Vec::new();
+        while let Some(command) = parser.next().unwrap() {
+            texts.push(&wast[command.text_range.unwrap()]);
+        }
+        assert_eq!(texts.len(), 4);
+        assert!(texts[0].starts_with("(module $m"));
+        assert!(texts[0].ends_with("i32.const 1))"));
+        assert_eq!(texts[1], r#"(assert_return (invoke "f") (i32.const 1))"#);
+        assert_eq!(texts[2], r#"(invoke "f")"#);
+        assert_eq!(texts[3], r#"(register "m" $m)"#);
+    }
+
+    #[test]
+    fn unknown_value_type() {
+        let value: json::RuntimeValue =
+            serde_json::from_str(r#"{"type": "i31ref", "value": "0"}"#).unwrap();
+        match parse_value::<f32, f64>(&value) {
+            Err(Error::Other(message)) => assert_eq!(message, "Unknown type 'i31ref'"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn nan_matching() {
+        let f32_value = |bits: u32| Value::F32(f32::from_bits(bits));
+        let f64_value = |bits: u64| Value::F64(f64::from_bits(bits));
+        let f32_canonical = ExpectedValue::F32Nan(NanPattern::Canonical);
+        let f32_arithmetic = ExpectedValue::F32Nan(NanPattern::Arithmetic);
+        let f64_canonical = ExpectedValue::F64Nan(NanPattern::Canonical);
+        let f64_arithmetic = ExpectedValue::F64Nan(NanPattern::Arithmetic);
+
+        assert!(matches(&f32_canonical, &f32_value(0x7fc0_0000)));
+        assert!(matches(&f32_canonical, &f32_value(0xffc0_0000)));
+        assert!(!matches(&f32_canonical, &f32_value(0x7fc0_0001)));
+        assert!(!matches(&f32_canonical, &f32_value(0x7f80_0001)));
+        assert!(!matches(&f32_canonical, &Value::F32(1.0)));
+        assert!(!matches(&f32_canonical, &f64_value(0x7ff8_0000_0000_0000)));
+
+        assert!(matches(&f32_arithmetic, &f32_value(0x7fc0_0000)));
+        assert!(matches(&f32_arithmetic, &f32_value(0xffc0_0001)));
+        assert!(!matches(&f32_arithmetic, &f32_value(0x7f80_0001)));
+        assert!(!matches(&f32_arithmetic, &Value::F32(f32::INFINITY)));
+
+        assert!(matches(&f64_canonical, &f64_value(0x7ff8_0000_0000_0000)));
+        assert!(matches(&f64_canonical, &f64_value(0xfff8_0000_0000_0000)));
+        assert!(!matches(&f64_canonical, &f64_value(0x7ff8_0000_0000_0001)));
+
+        assert!(matches(&f64_arithmetic, &f64_value(0x7ff8_0000_0000_0001)));
+        assert!(!matches(&f64_arithmetic, &f64_value(0x7ff0_0000_0000_0001)));
+        assert!(!matches(
+            &f64_arithmetic,
+            &Value::I64(0x7ff8_0000_0000_0000)
+        ));
+
+        let exact_nan = ExpectedValue::Value(f32_value(0x7fc0_0001));
+        assert!(matches(&exact_nan, &f32_value(0x7fc0_0001)));
+        assert!(!matches(&exact_nan, &f32_value(0x7fc0_0000)));
+        assert!(!matches(&Value::F64(0.0).into(), &Value::F64(-0.0)));
+        assert!(matches(&Value::I32(7).into(), &Value::I32(7)));
+    }
+
+    #[test]
+    fn assert_exception() {
+        let wast = r#"
+(module
+  (tag $e)
+  (func (export "throw")
+    throw $e))
+
+(assert_exception (invoke "throw"))
+"#;
+        let mut features = Features::new();
+        features.enable_exceptions();
+        let mut parser: ScriptParser = ScriptParser::from_source_and_name_with_features(
+            wast.as_bytes(),
+            "test.wast",
+            features,
+        )
+        .unwrap();
+        parser.next().unwrap().unwrap();
+        assert_eq!(
+            parser.next().unwrap().unwrap().kind,
+            CommandKind::AssertException {
+                action: Action::Invoke {
+                    module: None,
+                    field: "throw".into(),
+                    args: vec![],
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn malformed_json() {
+        for json_str in &[
+            &b"{\"commands\": ["[..],
+            br#"{"source_filename": "test.wast", "commands": [{"type": "module"}]}"#,
+        ] {
+            match parse_json_spec(json_str) {
+                Err(Error::Json(_)) => {}
+                Err(other) => panic!("unexpected error: {:?}", other),
+                Ok(_) => panic!("expected an error"),
+            }
+        }
+    }
+
     #[test]
     fn utf8_handling() {
         // See https://github.com/pepyakin/wabt-rs/issues/50
@@ -671,4 +1568,89 @@ mod tests {
         let mut parser: ScriptParser = ScriptParser::from_str(wast).unwrap();
         while let Some(Command { .. }) = parser.next().unwrap() {}
     }
+
+    #[test]
+    fn multibyte_utf8_names() {
+        let wast = "
+            (module
+                (func (export \"\u{20ac}\"))
+                (func (export \"\u{1f980}\")))
+
+            (invoke \"\u{20ac}\")
+            (invoke \"\u{1f980}\")
+        ";
+
+        let mut parser: ScriptParser = ScriptParser::from_str(wast).unwrap();
+        parser.next().unwrap().unwrap();
+        for expected in &["\u{20ac}", "\u{1f980}"] {
+            match parser.next().unwrap().unwrap().kind {
+                CommandKind::PerformAction(Action::Invoke { field, .. }) => {
+                    assert_eq!(field, *expected)
+                }
+                other => panic!("unexpected command: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn serde_roundtrip() {
+        let wast = r#"
+(module
+  (func (export "add") (param i32 f32) (result i32 f32)
+    local.get 0
+    local.get 1))
+
+(assert_return (invoke "add" (i32.const 1) (f32.const 1.5))
+  (i32.const 1) (f32.const 1.5))
+"#;
+        let mut parser: ScriptParser = ScriptParser::from_str(wast).unwrap();
+        parser.next().unwrap().unwrap();
+        let command = parser.next().unwrap().unwrap();
+        match command.kind {
+            CommandKind::AssertReturn { .. } => {}
+            ref other => panic!("unexpected command: {:?}", other),
+        }
+
+        let json = serde_json::to_string(&command).unwrap();
+        let roundtripped: Command = serde_json::from_str(&json).unwrap();
+        assert_eq!(roundtripped, command);
+    }
+
+    #[test]
+    fn value_hash_set() {
+        use std::collections::HashSet;
+
+        let mut set = HashSet::new();
+        set.insert(Value::<u32, u64>::I32(1));
+        set.insert(Value::I32(2));
+        set.insert(Value::I32(1));
+        assert_eq!(set.len(), 2);
+
+        // Floats are compared bitwise.
+        set.insert(Value::F32(0.0f32.to_bits()));
+        set.insert(Value::F32((-0.0f32).to_bits()));
+        set.insert(Value::F32(f32::NAN.to_bits()));
+        set.insert(Value::F32(f32::NAN.to_bits()));
+        assert_eq!(set.len(), 5);
+
+        let wast = r#"
+(module
+  (func (export "nan") (result f32)
+    f32.const nan))
+
+(assert_return (invoke "nan") (f32.const nan))
+(assert_return (invoke "nan") (f32.const nan))
+"#;
+        let mut parser: ScriptParser<u32, u64> = ScriptParser::from_str(wast).unwrap();
+        let mut expected = HashSet::new();
+        while let Some(Command { kind, .. }) = parser.next().unwrap() {
+            if let CommandKind::AssertReturn {
+                expected: values, ..
+            } = kind
+            {
+                expected.extend(values);
+            }
+        }
+        assert_eq!(expected.len(), 1);
+    }
 }
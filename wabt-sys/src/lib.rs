@@ -8,9 +8,15 @@ pub enum WabtParseWastResult {}
 pub enum WasmModule {}
 pub enum WabtWriteModuleResult {}
 pub enum WabtReadBinaryResult {}
+pub enum WabtReadBinaryIrResult {}
 pub enum OutputBuffer {}
 pub enum Script {}
 pub enum WabtWriteScriptResult {}
+pub enum WabtReadLinkingSymbolsResult {}
+pub enum WabtReadRelocationsResult {}
+pub enum WabtInstructionOffsets {}
+pub enum FuncSignature {}
+pub enum Limits {}
 
 #[derive(Debug, PartialEq, Eq)]
 #[repr(C)]
@@ -74,6 +80,22 @@ extern "C" {
 
     pub fn wabt_destroy_errors(errors: *mut Errors);
 
+    pub fn wabt_errors_clear(errors: *mut Errors);
+
+    pub fn wabt_errors_get_count(errors: *mut Errors) -> usize;
+
+    pub fn wabt_errors_get_message(errors: *mut Errors, index: usize) -> *const c_char;
+
+    pub fn wabt_errors_get_level(errors: *mut Errors, index: usize) -> c_int;
+
+    pub fn wabt_errors_has_text_location(errors: *mut Errors, index: usize) -> c_int;
+
+    pub fn wabt_errors_get_line(errors: *mut Errors, index: usize) -> c_int;
+
+    pub fn wabt_errors_get_column(errors: *mut Errors, index: usize) -> c_int;
+
+    pub fn wabt_errors_get_offset(errors: *mut Errors, index: usize) -> usize;
+
     pub fn wabt_parse_wat(
         lexer: *mut WastLexer,
         features: *mut Features,
@@ -115,18 +137,165 @@ extern "C" {
         write_debug_name: c_int,
     ) -> *mut WabtWriteModuleResult;
 
+    pub fn wabt_write_binary_module_without_local_names(
+        module: *mut WasmModule,
+        log: c_int,
+        canonicalize_lebs: c_int,
+        relocatable: c_int,
+    ) -> *mut WabtWriteModuleResult;
+
     pub fn wabt_write_module_result_get_result(result: *mut WabtWriteModuleResult) -> Result;
 
     pub fn wabt_write_module_result_release_output_buffer(
         result: *mut WabtWriteModuleResult,
     ) -> *mut OutputBuffer;
 
+    pub fn wabt_write_module_result_release_log_output_buffer(
+        result: *mut WabtWriteModuleResult,
+    ) -> *mut OutputBuffer;
+
     pub fn wabt_destroy_write_module_result(result: *mut WabtWriteModuleResult);
 
     pub fn wabt_output_buffer_get_data(buffer: *mut OutputBuffer) -> *const c_void;
 
     pub fn wabt_output_buffer_get_size(buffer: *mut OutputBuffer) -> usize;
 
+    pub fn wabt_decompile_module(module: *mut WasmModule) -> *mut OutputBuffer;
+
+    pub fn wabt_module_get_type_count(module: *mut WasmModule) -> usize;
+
+    pub fn wabt_module_get_func_type_signature(
+        module: *mut WasmModule,
+        index: usize,
+    ) -> *const FuncSignature;
+
+    pub fn wabt_func_signature_get_param_count(sig: *const FuncSignature) -> usize;
+
+    pub fn wabt_func_signature_get_param_type(sig: *const FuncSignature, index: usize) -> i32;
+
+    pub fn wabt_func_signature_get_result_count(sig: *const FuncSignature) -> usize;
+
+    pub fn wabt_func_signature_get_result_type(sig: *const FuncSignature, index: usize) -> i32;
+
+    pub fn wabt_module_get_import_count(module: *mut WasmModule) -> usize;
+
+    pub fn wabt_module_get_import_module_name(
+        module: *mut WasmModule,
+        index: usize,
+    ) -> *const c_char;
+
+    pub fn wabt_module_get_import_field_name(
+        module: *mut WasmModule,
+        index: usize,
+    ) -> *const c_char;
+
+    pub fn wabt_module_get_import_kind(module: *mut WasmModule, index: usize) -> c_int;
+
+    pub fn wabt_module_get_import_func_signature(
+        module: *mut WasmModule,
+        index: usize,
+    ) -> *const FuncSignature;
+
+    pub fn wabt_module_get_import_limits(module: *mut WasmModule, index: usize) -> *const Limits;
+
+    pub fn wabt_module_get_import_table_elem_type(module: *mut WasmModule, index: usize) -> i32;
+
+    pub fn wabt_module_get_import_global_type(module: *mut WasmModule, index: usize) -> i32;
+
+    pub fn wabt_module_get_import_global_mutable(module: *mut WasmModule, index: usize) -> c_int;
+
+    pub fn wabt_module_get_item_count(module: *mut WasmModule, kind: c_int) -> usize;
+
+    pub fn wabt_module_get_imported_item_count(module: *mut WasmModule, kind: c_int) -> usize;
+
+    pub fn wabt_module_get_export_count(module: *mut WasmModule) -> usize;
+
+    pub fn wabt_module_get_export_name(module: *mut WasmModule, index: usize) -> *const c_char;
+
+    pub fn wabt_module_get_export_kind(module: *mut WasmModule, index: usize) -> c_int;
+
+    pub fn wabt_module_get_export_item_index(module: *mut WasmModule, index: usize) -> u32;
+
+    pub fn wabt_module_get_export_func_signature(
+        module: *mut WasmModule,
+        index: usize,
+    ) -> *const FuncSignature;
+
+    pub fn wabt_module_rename_export(
+        module: *mut WasmModule,
+        old_name: *const c_char,
+        new_name: *const c_char,
+    ) -> c_int;
+
+    pub fn wabt_module_add_custom_section(
+        module: *mut WasmModule,
+        name: *const c_char,
+        data: *const u8,
+        size: usize,
+    );
+
+    pub fn wabt_module_clamp_memory_pages(module: *mut WasmModule, max_pages: u64);
+
+    pub fn wabt_module_get_data_segment_count(module: *mut WasmModule) -> usize;
+
+    pub fn wabt_module_get_data_segment_kind(module: *mut WasmModule, index: usize) -> c_int;
+
+    pub fn wabt_module_get_data_segment_memory_index(module: *mut WasmModule, index: usize)
+        -> u32;
+
+    pub fn wabt_module_get_data_segment_offset_kind(module: *mut WasmModule, index: usize)
+        -> c_int;
+
+    pub fn wabt_module_get_data_segment_offset_value(
+        module: *mut WasmModule,
+        index: usize,
+    ) -> u64;
+
+    pub fn wabt_module_get_data_segment_size(module: *mut WasmModule, index: usize) -> usize;
+
+    pub fn wabt_module_get_data_segment_data(module: *mut WasmModule, index: usize) -> *const u8;
+
+    pub fn wabt_limits_get_initial(limits: *const Limits) -> u64;
+
+    pub fn wabt_limits_has_max(limits: *const Limits) -> c_int;
+
+    pub fn wabt_limits_get_max(limits: *const Limits) -> u64;
+
+    pub fn wabt_read_instruction_offsets(
+        data: *const u8,
+        size: usize,
+        features: *mut Features,
+        errors: *mut Errors,
+    ) -> *mut WabtInstructionOffsets;
+
+    pub fn wabt_instruction_offsets_get_result(result: *mut WabtInstructionOffsets) -> Result;
+
+    pub fn wabt_instruction_offsets_get_func_count(result: *mut WabtInstructionOffsets)
+        -> usize;
+
+    pub fn wabt_instruction_offsets_get_count(
+        result: *mut WabtInstructionOffsets,
+        func: usize,
+    ) -> usize;
+
+    pub fn wabt_instruction_offsets_get_offset(
+        result: *mut WabtInstructionOffsets,
+        func: usize,
+        index: usize,
+    ) -> usize;
+
+    pub fn wabt_instruction_offsets_get_opcode_name(
+        result: *mut WabtInstructionOffsets,
+        func: usize,
+        index: usize,
+    ) -> *const c_char;
+
+    pub fn wabt_destroy_instruction_offsets(result: *mut WabtInstructionOffsets);
+
+    pub fn wabt_version_string() -> *const c_char;
+
+    pub fn wabt_new_output_buffer(data: *const u8, size: usize) -> *mut OutputBuffer;
+
     pub fn wabt_destroy_output_buffer(buffer: *mut OutputBuffer);
 
     pub fn wabt_resolve_names_script(script: *mut Script, errors: *mut Errors) -> Result;
@@ -155,6 +324,30 @@ extern "C" {
         errors: *mut Errors,
     ) -> *mut WabtReadBinaryResult;
 
+    pub fn wabt_read_binary_ir(
+        data: *const u8,
+        size: usize,
+        read_debug_names: c_int,
+        stop_on_first_error: c_int,
+        fail_on_custom_section_error: c_int,
+        features: *mut Features,
+        errors: *mut Errors,
+    ) -> *mut WabtReadBinaryIrResult;
+
+    pub fn wabt_read_binary_prefix_len(
+        data: *const u8,
+        size: usize,
+        features: *mut Features,
+    ) -> usize;
+
+    pub fn wabt_read_binary_ir_result_get_result(result: *mut WabtReadBinaryIrResult) -> Result;
+
+    pub fn wabt_read_binary_ir_result_release_module(
+        result: *mut WabtReadBinaryIrResult,
+    ) -> *mut WasmModule;
+
+    pub fn wabt_destroy_read_binary_ir_result(result: *mut WabtReadBinaryIrResult);
+
     pub fn wabt_parse_wast_result_get_result(result: *mut WabtParseWastResult) -> Result;
 
     pub fn wabt_parse_wast_result_release_module(result: *mut WabtParseWastResult) -> *mut Script;
@@ -175,6 +368,79 @@ extern "C" {
         inline_export: c_int,
     ) -> *mut WabtWriteModuleResult;
 
+    pub fn wabt_read_linking_symbols(
+        data: *const u8,
+        size: usize,
+        features: *mut Features,
+        errors: *mut Errors,
+    ) -> *mut WabtReadLinkingSymbolsResult;
+
+    pub fn wabt_read_linking_symbols_result_get_result(
+        result: *mut WabtReadLinkingSymbolsResult,
+    ) -> Result;
+
+    pub fn wabt_read_linking_symbols_result_get_count(
+        result: *mut WabtReadLinkingSymbolsResult,
+    ) -> usize;
+
+    pub fn wabt_read_linking_symbols_result_get_name(
+        result: *mut WabtReadLinkingSymbolsResult,
+        index: usize,
+    ) -> *const c_char;
+
+    pub fn wabt_read_linking_symbols_result_get_kind(
+        result: *mut WabtReadLinkingSymbolsResult,
+        index: usize,
+    ) -> c_int;
+
+    pub fn wabt_read_linking_symbols_result_get_flags(
+        result: *mut WabtReadLinkingSymbolsResult,
+        index: usize,
+    ) -> u32;
+
+    pub fn wabt_destroy_read_linking_symbols_result(result: *mut WabtReadLinkingSymbolsResult);
+
+    pub fn wabt_read_relocations(
+        data: *const u8,
+        size: usize,
+        features: *mut Features,
+        errors: *mut Errors,
+    ) -> *mut WabtReadRelocationsResult;
+
+    pub fn wabt_read_relocations_result_get_result(
+        result: *mut WabtReadRelocationsResult,
+    ) -> Result;
+
+    pub fn wabt_read_relocations_result_get_count(result: *mut WabtReadRelocationsResult)
+        -> usize;
+
+    pub fn wabt_read_relocations_result_get_section(
+        result: *mut WabtReadRelocationsResult,
+        index: usize,
+    ) -> u32;
+
+    pub fn wabt_read_relocations_result_get_type_name(
+        result: *mut WabtReadRelocationsResult,
+        index: usize,
+    ) -> *const c_char;
+
+    pub fn wabt_read_relocations_result_get_offset(
+        result: *mut WabtReadRelocationsResult,
+        index: usize,
+    ) -> u32;
+
+    pub fn wabt_read_relocations_result_get_index(
+        result: *mut WabtReadRelocationsResult,
+        index: usize,
+    ) -> u32;
+
+    pub fn wabt_read_relocations_result_get_addend(
+        result: *mut WabtReadRelocationsResult,
+        index: usize,
+    ) -> i32;
+
+    pub fn wabt_destroy_read_relocations_result(result: *mut WabtReadRelocationsResult);
+
     // WabtWriteScriptResult
 
     pub fn wabt_write_script_result_get_result(result: *mut WabtWriteScriptResult) -> Result;
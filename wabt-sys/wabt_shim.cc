@@ -1,9 +1,256 @@
+#include <algorithm>
+#include <memory>
+#include <string>
+#include <string_view>
+#include <utility>
+#include <vector>
+
+#include "config.h"
+
+#include "src/binary-reader-nop.h"
+#include "src/binary-reader-ir.h"
+#include "src/binary-reader.h"
+#include "src/decompiler.h"
+#include "src/generate-names.h"
+#include "src/leb128.h"
+#include "src/apply-names.h"
+#include "src/ir.h"
+#include "src/stream.h"
 #include "src/wast-lexer.h"
 #include "src/wast-parser.h"
 #include "src/resolve-names.h"
 
+struct WabtWriteModuleResult;
+
+struct WabtReadBinaryIrResult {
+  wabt::Result result;
+  std::unique_ptr<wabt::Module> module;
+};
+
+struct WabtLinkingSymbol {
+  std::string name;
+  wabt::SymbolType kind;
+  uint32_t flags;
+};
+
+struct WabtReadLinkingSymbolsResult {
+  wabt::Result result;
+  std::vector<WabtLinkingSymbol> symbols;
+};
+
+struct WabtRelocation {
+  uint32_t section;
+  wabt::RelocType type;
+  uint32_t offset;
+  uint32_t index;
+  int32_t addend;
+};
+
+struct WabtReadRelocationsResult {
+  wabt::Result result;
+  std::vector<WabtRelocation> relocations;
+};
+
+struct WabtInstruction {
+  wabt::Offset offset;
+  wabt::Opcode opcode;
+};
+
+struct WabtInstructionOffsets {
+  wabt::Result result;
+  std::vector<std::vector<WabtInstruction>> funcs;
+};
+
+namespace {
+
+// Collects the offset and the opcode of every instruction that wasm2wat writes
+// on its own line, i.e. every instruction except the final `end` of a function
+// and an `else` that starts an empty branch.
+class InstructionOffsetsReader : public wabt::BinaryReaderNop {
+ public:
+  InstructionOffsetsReader(std::vector<std::vector<WabtInstruction>>* funcs,
+                           wabt::Errors* errors)
+      : funcs_(funcs), errors_(errors) {}
+
+  bool OnError(const wabt::Error& error) override {
+    errors_->push_back(error);
+    return true;
+  }
+
+  wabt::Result BeginFunctionBody(wabt::Index index,
+                                 wabt::Offset size) override {
+    funcs_->emplace_back();
+    last_opcode_ = wabt::Opcode::Nop;
+    return wabt::Result::Ok;
+  }
+
+  wabt::Result OnOpcode(wabt::Opcode opcode) override {
+    wabt::Offset length = 1;
+    if (opcode.HasPrefix()) {
+      length += wabt::U32Leb128Length(opcode.GetCode());
+    }
+    std::vector<WabtInstruction>& instructions = funcs_->back();
+    if (opcode == wabt::Opcode::End && last_opcode_ == wabt::Opcode::Else) {
+      instructions.pop_back();
+    }
+    instructions.push_back(WabtInstruction{state->offset - length, opcode});
+    last_opcode_ = opcode;
+    return wabt::Result::Ok;
+  }
+
+  wabt::Result EndFunctionBody(wabt::Index index) override {
+    funcs_->back().pop_back();
+    return wabt::Result::Ok;
+  }
+
+ private:
+  std::vector<std::vector<WabtInstruction>>* funcs_;
+  wabt::Errors* errors_;
+  wabt::Opcode last_opcode_ = wabt::Opcode::Nop;
+};
+
+
+class LinkingSymbolsReader : public wabt::BinaryReaderNop {
+ public:
+  LinkingSymbolsReader(std::vector<WabtLinkingSymbol>* symbols,
+                       wabt::Errors* errors)
+      : symbols_(symbols), errors_(errors) {}
+
+  bool OnError(const wabt::Error& error) override {
+    errors_->push_back(error);
+    return true;
+  }
+
+  wabt::Result OnDataSymbol(wabt::Index index,
+                            uint32_t flags,
+                            std::string_view name,
+                            wabt::Index segment,
+                            uint32_t offset,
+                            uint32_t size) override {
+    return AddSymbol(name, wabt::SymbolType::Data, flags);
+  }
+
+  wabt::Result OnFunctionSymbol(wabt::Index index,
+                                uint32_t flags,
+                                std::string_view name,
+                                wabt::Index func_index) override {
+    return AddSymbol(name, wabt::SymbolType::Function, flags);
+  }
+
+  wabt::Result OnGlobalSymbol(wabt::Index index,
+                              uint32_t flags,
+                              std::string_view name,
+                              wabt::Index global_index) override {
+    return AddSymbol(name, wabt::SymbolType::Global, flags);
+  }
+
+  wabt::Result OnSectionSymbol(wabt::Index index,
+                               uint32_t flags,
+                               wabt::Index section_index) override {
+    return AddSymbol(std::string_view(), wabt::SymbolType::Section, flags);
+  }
+
+  wabt::Result OnTagSymbol(wabt::Index index,
+                           uint32_t flags,
+                           std::string_view name,
+                           wabt::Index tag_index) override {
+    return AddSymbol(name, wabt::SymbolType::Tag, flags);
+  }
+
+  wabt::Result OnTableSymbol(wabt::Index index,
+                             uint32_t flags,
+                             std::string_view name,
+                             wabt::Index table_index) override {
+    return AddSymbol(name, wabt::SymbolType::Table, flags);
+  }
+
+ private:
+  wabt::Result AddSymbol(std::string_view name,
+                         wabt::SymbolType kind,
+                         uint32_t flags) {
+    symbols_->push_back(WabtLinkingSymbol{std::string(name), kind, flags});
+    return wabt::Result::Ok;
+  }
+
+  std::vector<WabtLinkingSymbol>* symbols_;
+  wabt::Errors* errors_;
+};
+
+// Records where the sections that the reader got to end.
+class SectionEndsReader : public wabt::BinaryReaderNop {
+ public:
+  bool OnError(const wabt::Error& error) override { return true; }
+
+  wabt::Result BeginSection(wabt::Index section_index,
+                            wabt::BinarySection section_type,
+                            wabt::Offset size) override {
+    // Getting to a section means that the previous one was read completely.
+    previous_section_end_ = section_end_;
+    section_end_ = state->offset + size;
+    return wabt::Result::Ok;
+  }
+
+  wabt::Offset previous_section_end() const { return previous_section_end_; }
+  wabt::Offset section_end() const { return section_end_; }
+
+ private:
+  // The sections start after the magic number and the version.
+  wabt::Offset previous_section_end_ = 8;
+  wabt::Offset section_end_ = 8;
+};
+
+bool ReadsSections(const void* data,
+                   size_t size,
+                   const wabt::Features& features,
+                   SectionEndsReader* reader) {
+  wabt::ReadBinaryOptions options(features, nullptr, false, true, true);
+  return wabt::Succeeded(wabt::ReadBinary(data, size, reader, options));
+}
+
+class RelocationsReader : public wabt::BinaryReaderNop {
+ public:
+  RelocationsReader(std::vector<WabtRelocation>* relocations,
+                    wabt::Errors* errors)
+      : relocations_(relocations), errors_(errors) {}
+
+  bool OnError(const wabt::Error& error) override {
+    errors_->push_back(error);
+    return true;
+  }
+
+  wabt::Result OnRelocCount(wabt::Index count,
+                            wabt::Index section_index) override {
+    section_ = section_index;
+    return wabt::Result::Ok;
+  }
+
+  wabt::Result OnReloc(wabt::RelocType type,
+                       wabt::Offset offset,
+                       wabt::Index index,
+                       uint32_t addend) override {
+    relocations_->push_back(WabtRelocation{
+        section_, type, static_cast<uint32_t>(offset), index,
+        static_cast<int32_t>(addend)});
+    return wabt::Result::Ok;
+  }
+
+ private:
+  std::vector<WabtRelocation>* relocations_;
+  wabt::Errors* errors_;
+  wabt::Index section_ = 0;
+};
+
+}  // namespace
+
 extern "C" {
 
+// Defined in emscripten-helpers.cc.
+WabtWriteModuleResult* wabt_write_binary_module(wabt::Module* module,
+                                                int log,
+                                                int canonicalize_lebs,
+                                                int relocatable,
+                                                int write_debug_names);
+
 wabt::Result::Enum wabt_resolve_names_script(
     wabt::Script* script,
     wabt::Errors* errors) {
@@ -16,4 +263,560 @@ wabt::Result::Enum wabt_resolve_names_module(
   return ResolveNamesModule(module, errors);
 }
 
+WabtWriteModuleResult* wabt_write_binary_module_without_local_names(
+    wabt::Module* module,
+    int log,
+    int canonicalize_lebs,
+    int relocatable) {
+  // Local names are written from the function bindings, so hide them for the
+  // duration of the write and put them back afterwards.
+  std::vector<wabt::BindingHash> bindings;
+  for (wabt::Func* func : module->funcs) {
+    bindings.push_back(std::move(func->bindings));
+    func->bindings.clear();
+  }
+
+  WabtWriteModuleResult* result =
+      wabt_write_binary_module(module, log, canonicalize_lebs, relocatable, 1);
+
+  for (size_t i = 0; i < module->funcs.size(); ++i) {
+    module->funcs[i]->bindings = std::move(bindings[i]);
+  }
+  return result;
+}
+
+// Like wabt_read_binary, but with all of the reader options.
+WabtReadBinaryIrResult* wabt_read_binary_ir(const void* data,
+                                            size_t size,
+                                            int read_debug_names,
+                                            int stop_on_first_error,
+                                            int fail_on_custom_section_error,
+                                            wabt::Features* features,
+                                            wabt::Errors* errors) {
+  wabt::ReadBinaryOptions options(*features, nullptr, read_debug_names,
+                                  stop_on_first_error,
+                                  fail_on_custom_section_error);
+  WabtReadBinaryIrResult* result = new WabtReadBinaryIrResult();
+  result->module.reset(new wabt::Module());
+  result->result = wabt::ReadBinaryIr("<binary>", data, size, options, errors,
+                                      result->module.get());
+  return result;
+}
+
+// Returns the length of the longest prefix of `data` that wabt reads as a whole
+// module, i.e. the offset where the last section that could be read ends. Any
+// bytes after it, such as another module, aren't part of the module.
+size_t wabt_read_binary_prefix_len(const void* data,
+                                   size_t size,
+                                   wabt::Features* features) {
+  SectionEndsReader reader;
+  if (ReadsSections(data, size, *features, &reader)) {
+    return size;
+  }
+  // The reader failed either within the last section it got to, or right
+  // after it when the trailing bytes don't form a section header.
+  wabt::Offset section_end = std::min<wabt::Offset>(reader.section_end(), size);
+  SectionEndsReader prefix_reader;
+  if (section_end > reader.previous_section_end() &&
+      ReadsSections(data, section_end, *features, &prefix_reader)) {
+    return section_end;
+  }
+  return std::min<wabt::Offset>(reader.previous_section_end(), size);
+}
+
+wabt::Result::Enum wabt_read_binary_ir_result_get_result(
+    WabtReadBinaryIrResult* result) {
+  return result->result;
+}
+
+wabt::Module* wabt_read_binary_ir_result_release_module(
+    WabtReadBinaryIrResult* result) {
+  return result->module.release();
+}
+
+void wabt_destroy_read_binary_ir_result(WabtReadBinaryIrResult* result) {
+  delete result;
+}
+
+WabtReadLinkingSymbolsResult* wabt_read_linking_symbols(
+    const void* data,
+    size_t size,
+    wabt::Features* features,
+    wabt::Errors* errors) {
+  WabtReadLinkingSymbolsResult* result = new WabtReadLinkingSymbolsResult();
+  LinkingSymbolsReader reader(&result->symbols, errors);
+  wabt::ReadBinaryOptions options(*features, nullptr, false, true, true);
+  result->result = wabt::ReadBinary(data, size, &reader, options);
+  return result;
+}
+
+wabt::Result::Enum wabt_read_linking_symbols_result_get_result(
+    WabtReadLinkingSymbolsResult* result) {
+  return result->result;
+}
+
+size_t wabt_read_linking_symbols_result_get_count(
+    WabtReadLinkingSymbolsResult* result) {
+  return result->symbols.size();
+}
+
+const char* wabt_read_linking_symbols_result_get_name(
+    WabtReadLinkingSymbolsResult* result,
+    size_t index) {
+  return result->symbols[index].name.c_str();
+}
+
+int wabt_read_linking_symbols_result_get_kind(
+    WabtReadLinkingSymbolsResult* result,
+    size_t index) {
+  return static_cast<int>(result->symbols[index].kind);
+}
+
+uint32_t wabt_read_linking_symbols_result_get_flags(
+    WabtReadLinkingSymbolsResult* result,
+    size_t index) {
+  return result->symbols[index].flags;
+}
+
+void wabt_destroy_read_linking_symbols_result(
+    WabtReadLinkingSymbolsResult* result) {
+  delete result;
+}
+
+wabt::OutputBuffer* wabt_decompile_module(wabt::Module* module) {
+  // wasm-decompile names unnamed items with short alphabetic names rather than
+  // the `$f0`-style names used by wasm2wat.
+  if (wabt::Failed(wabt::GenerateNames(module, wabt::NameOpts::AlphaNames)) ||
+      wabt::Failed(wabt::ApplyNames(module))) {
+    return nullptr;
+  }
+
+  wabt::DecompileOptions options;
+  std::string text = wabt::Decompile(*module, options);
+  wabt::OutputBuffer* buffer = new wabt::OutputBuffer();
+  buffer->data.assign(text.begin(), text.end());
+  return buffer;
+}
+
+size_t wabt_module_get_type_count(wabt::Module* module) {
+  return module->types.size();
+}
+
+// Returns null if the type isn't a function type, e.g. a GC struct type.
+const wabt::FuncSignature* wabt_module_get_func_type_signature(
+    wabt::Module* module,
+    size_t index) {
+  auto* func_type = wabt::dyn_cast<wabt::FuncType>(module->types[index]);
+  return func_type ? &func_type->sig : nullptr;
+}
+
+size_t wabt_func_signature_get_param_count(const wabt::FuncSignature* sig) {
+  return sig->param_types.size();
+}
+
+int32_t wabt_func_signature_get_param_type(const wabt::FuncSignature* sig,
+                                           size_t index) {
+  return static_cast<wabt::Type::Enum>(sig->param_types[index]);
+}
+
+size_t wabt_func_signature_get_result_count(const wabt::FuncSignature* sig) {
+  return sig->result_types.size();
+}
+
+int32_t wabt_func_signature_get_result_type(const wabt::FuncSignature* sig,
+                                            size_t index) {
+  return static_cast<wabt::Type::Enum>(sig->result_types[index]);
+}
+
+size_t wabt_module_get_import_count(wabt::Module* module) {
+  return module->imports.size();
+}
+
+const char* wabt_module_get_import_module_name(wabt::Module* module,
+                                               size_t index) {
+  return module->imports[index]->module_name.c_str();
+}
+
+const char* wabt_module_get_import_field_name(wabt::Module* module,
+                                              size_t index) {
+  return module->imports[index]->field_name.c_str();
+}
+
+int wabt_module_get_import_kind(wabt::Module* module, size_t index) {
+  return static_cast<int>(module->imports[index]->kind());
+}
+
+// Returns null if the import isn't a function.
+const wabt::FuncSignature* wabt_module_get_import_func_signature(
+    wabt::Module* module,
+    size_t index) {
+  auto* import = wabt::dyn_cast<wabt::FuncImport>(module->imports[index]);
+  return import ? &import->func.decl.sig : nullptr;
+}
+
+// Returns null if the import isn't a table or a memory.
+const wabt::Limits* wabt_module_get_import_limits(wabt::Module* module,
+                                                  size_t index) {
+  wabt::Import* import = module->imports[index];
+  if (auto* table_import = wabt::dyn_cast<wabt::TableImport>(import)) {
+    return &table_import->table.elem_limits;
+  }
+  if (auto* memory_import = wabt::dyn_cast<wabt::MemoryImport>(import)) {
+    return &memory_import->memory.page_limits;
+  }
+  return nullptr;
+}
+
+int32_t wabt_module_get_import_table_elem_type(wabt::Module* module,
+                                               size_t index) {
+  auto* import = wabt::cast<wabt::TableImport>(module->imports[index]);
+  return static_cast<wabt::Type::Enum>(import->table.elem_type);
+}
+
+int32_t wabt_module_get_import_global_type(wabt::Module* module,
+                                           size_t index) {
+  auto* import = wabt::cast<wabt::GlobalImport>(module->imports[index]);
+  return static_cast<wabt::Type::Enum>(import->global.type);
+}
+
+int wabt_module_get_import_global_mutable(wabt::Module* module,
+                                          size_t index) {
+  auto* import = wabt::cast<wabt::GlobalImport>(module->imports[index]);
+  return import->global.mutable_;
+}
+
+// Returns the number of items of the given `wabt::ExternalKind`, including
+// imported ones.
+size_t wabt_module_get_item_count(wabt::Module* module, int kind) {
+  switch (static_cast<wabt::ExternalKind>(kind)) {
+    case wabt::ExternalKind::Func:
+      return module->funcs.size();
+    case wabt::ExternalKind::Table:
+      return module->tables.size();
+    case wabt::ExternalKind::Memory:
+      return module->memories.size();
+    case wabt::ExternalKind::Global:
+      return module->globals.size();
+    case wabt::ExternalKind::Tag:
+      return module->tags.size();
+  }
+  return 0;
+}
+
+size_t wabt_module_get_imported_item_count(wabt::Module* module, int kind) {
+  switch (static_cast<wabt::ExternalKind>(kind)) {
+    case wabt::ExternalKind::Func:
+      return module->num_func_imports;
+    case wabt::ExternalKind::Table:
+      return module->num_table_imports;
+    case wabt::ExternalKind::Memory:
+      return module->num_memory_imports;
+    case wabt::ExternalKind::Global:
+      return module->num_global_imports;
+    case wabt::ExternalKind::Tag:
+      return module->num_tag_imports;
+  }
+  return 0;
+}
+
+size_t wabt_module_get_export_count(wabt::Module* module) {
+  return module->exports.size();
+}
+
+const char* wabt_module_get_export_name(wabt::Module* module, size_t index) {
+  return module->exports[index]->name.c_str();
+}
+
+int wabt_module_get_export_kind(wabt::Module* module, size_t index) {
+  return static_cast<int>(module->exports[index]->kind);
+}
+
+// Returns the index of the exported item, or `kInvalidIndex` if the export
+// refers to an unknown item.
+uint32_t wabt_module_get_export_item_index(wabt::Module* module,
+                                           size_t index) {
+  const wabt::Export* export_ = module->exports[index];
+  switch (export_->kind) {
+    case wabt::ExternalKind::Func:
+      return module->GetFuncIndex(export_->var);
+    case wabt::ExternalKind::Table:
+      return module->GetTableIndex(export_->var);
+    case wabt::ExternalKind::Memory:
+      return module->GetMemoryIndex(export_->var);
+    case wabt::ExternalKind::Global:
+      return module->GetGlobalIndex(export_->var);
+    case wabt::ExternalKind::Tag:
+      return module->GetTagIndex(export_->var);
+  }
+  return wabt::kInvalidIndex;
+}
+
+// Returns null if the export isn't a known function.
+const wabt::FuncSignature* wabt_module_get_export_func_signature(
+    wabt::Module* module,
+    size_t index) {
+  const wabt::Export* export_ = module->exports[index];
+  if (export_->kind != wabt::ExternalKind::Func) {
+    return nullptr;
+  }
+  const wabt::Func* func = module->GetFunc(export_->var);
+  return func ? &func->decl.sig : nullptr;
+}
+
+// Returns 0 on success, 1 if there is no export named `old_name` and 2 if
+// there already is an export named `new_name`.
+int wabt_module_rename_export(wabt::Module* module,
+                              const char* old_name,
+                              const char* new_name) {
+  if (module->export_bindings.count(new_name) != 0) {
+    return 2;
+  }
+  auto iter = module->export_bindings.find(old_name);
+  if (iter == module->export_bindings.end()) {
+    return 1;
+  }
+  wabt::Binding binding = iter->second;
+  module->export_bindings.erase(iter);
+  module->export_bindings.emplace(new_name, binding);
+  module->exports[binding.index]->name = new_name;
+  return 0;
+}
+
+void wabt_module_add_custom_section(wabt::Module* module,
+                                   const char* name,
+                                   const uint8_t* data,
+                                   size_t size) {
+  wabt::Custom custom;
+  custom.name = name;
+  custom.data.assign(data, data + size);
+  module->customs.push_back(std::move(custom));
+}
+
+// Clamps the initial and maximum sizes of all memories to `max_pages`.
+void wabt_module_clamp_memory_pages(wabt::Module* module, uint64_t max_pages) {
+  for (wabt::Memory* memory : module->memories) {
+    wabt::Limits& limits = memory->page_limits;
+    if (limits.initial > max_pages) {
+      limits.initial = max_pages;
+    }
+    if (limits.has_max && limits.max > max_pages) {
+      limits.max = max_pages;
+    }
+  }
+}
+
+size_t wabt_module_get_data_segment_count(wabt::Module* module) {
+  return module->data_segments.size();
+}
+
+int wabt_module_get_data_segment_kind(wabt::Module* module, size_t index) {
+  return static_cast<int>(module->data_segments[index]->kind);
+}
+
+uint32_t wabt_module_get_data_segment_memory_index(wabt::Module* module,
+                                                   size_t index) {
+  return module->GetMemoryIndex(module->data_segments[index]->memory_var);
+}
+
+// Returns 0 for `i32.const`, 1 for `i64.const` and 2 for `global.get` offsets,
+// or -1 if the offset is any other expression.
+int wabt_module_get_data_segment_offset_kind(wabt::Module* module,
+                                             size_t index) {
+  const wabt::ExprList& offset = module->data_segments[index]->offset;
+  if (offset.size() != 1) {
+    return -1;
+  }
+  const wabt::Expr* expr = &offset.front();
+  if (auto* const_expr = wabt::dyn_cast<wabt::ConstExpr>(expr)) {
+    if (const_expr->const_.type() == wabt::Type::I32) {
+      return 0;
+    }
+    if (const_expr->const_.type() == wabt::Type::I64) {
+      return 1;
+    }
+  } else if (wabt::isa<wabt::GlobalGetExpr>(expr)) {
+    return 2;
+  }
+  return -1;
+}
+
+// Returns the constant or the global index of the offset, depending on
+// `wabt_module_get_data_segment_offset_kind`.
+uint64_t wabt_module_get_data_segment_offset_value(wabt::Module* module,
+                                                   size_t index) {
+  const wabt::Expr* expr = &module->data_segments[index]->offset.front();
+  if (auto* const_expr = wabt::dyn_cast<wabt::ConstExpr>(expr)) {
+    return const_expr->const_.type() == wabt::Type::I32
+               ? const_expr->const_.u32()
+               : const_expr->const_.u64();
+  }
+  auto* global_get = wabt::cast<wabt::GlobalGetExpr>(expr);
+  return module->GetGlobalIndex(global_get->var);
+}
+
+size_t wabt_module_get_data_segment_size(wabt::Module* module, size_t index) {
+  return module->data_segments[index]->data.size();
+}
+
+const uint8_t* wabt_module_get_data_segment_data(wabt::Module* module,
+                                                 size_t index) {
+  return module->data_segments[index]->data.data();
+}
+
+uint64_t wabt_limits_get_initial(const wabt::Limits* limits) {
+  return limits->initial;
+}
+
+int wabt_limits_has_max(const wabt::Limits* limits) {
+  return limits->has_max;
+}
+
+uint64_t wabt_limits_get_max(const wabt::Limits* limits) {
+  return limits->max;
+}
+
+WabtInstructionOffsets* wabt_read_instruction_offsets(
+    const void* data,
+    size_t size,
+    wabt::Features* features,
+    wabt::Errors* errors) {
+  WabtInstructionOffsets* result = new WabtInstructionOffsets();
+  InstructionOffsetsReader reader(&result->funcs, errors);
+  wabt::ReadBinaryOptions options(*features, nullptr, false, true, true);
+  result->result = wabt::ReadBinary(data, size, &reader, options);
+  return result;
+}
+
+wabt::Result::Enum wabt_instruction_offsets_get_result(
+    WabtInstructionOffsets* result) {
+  return result->result;
+}
+
+size_t wabt_instruction_offsets_get_func_count(
+    WabtInstructionOffsets* result) {
+  return result->funcs.size();
+}
+
+size_t wabt_instruction_offsets_get_count(WabtInstructionOffsets* result,
+                                          size_t func) {
+  return result->funcs[func].size();
+}
+
+size_t wabt_instruction_offsets_get_offset(WabtInstructionOffsets* result,
+                                           size_t func,
+                                           size_t index) {
+  return result->funcs[func][index].offset;
+}
+
+// Returns the name of the instruction as written by wasm2wat, such as
+// `i32.const`.
+const char* wabt_instruction_offsets_get_opcode_name(
+    WabtInstructionOffsets* result,
+    size_t func,
+    size_t index) {
+  return result->funcs[func][index].opcode.GetName();
+}
+
+void wabt_destroy_instruction_offsets(WabtInstructionOffsets* result) {
+  delete result;
+}
+
+WabtReadRelocationsResult* wabt_read_relocations(const void* data,
+                                                 size_t size,
+                                                 wabt::Features* features,
+                                                 wabt::Errors* errors) {
+  WabtReadRelocationsResult* result = new WabtReadRelocationsResult();
+  RelocationsReader reader(&result->relocations, errors);
+  wabt::ReadBinaryOptions options(*features, nullptr, false, true, true);
+  result->result = wabt::ReadBinary(data, size, &reader, options);
+  return result;
+}
+
+wabt::Result::Enum wabt_read_relocations_result_get_result(
+    WabtReadRelocationsResult* result) {
+  return result->result;
+}
+
+size_t wabt_read_relocations_result_get_count(
+    WabtReadRelocationsResult* result) {
+  return result->relocations.size();
+}
+
+uint32_t wabt_read_relocations_result_get_section(
+    WabtReadRelocationsResult* result,
+    size_t index) {
+  return result->relocations[index].section;
+}
+
+const char* wabt_read_relocations_result_get_type_name(
+    WabtReadRelocationsResult* result,
+    size_t index) {
+  return wabt::GetRelocTypeName(result->relocations[index].type);
+}
+
+uint32_t wabt_read_relocations_result_get_offset(
+    WabtReadRelocationsResult* result,
+    size_t index) {
+  return result->relocations[index].offset;
+}
+
+uint32_t wabt_read_relocations_result_get_index(
+    WabtReadRelocationsResult* result,
+    size_t index) {
+  return result->relocations[index].index;
+}
+
+int32_t wabt_read_relocations_result_get_addend(
+    WabtReadRelocationsResult* result,
+    size_t index) {
+  return result->relocations[index].addend;
+}
+
+void wabt_destroy_read_relocations_result(WabtReadRelocationsResult* result) {
+  delete result;
+}
+
+const char* wabt_version_string() {
+  return WABT_VERSION_STRING;
+}
+
+wabt::OutputBuffer* wabt_new_output_buffer(const uint8_t* data, size_t size) {
+  wabt::OutputBuffer* buffer = new wabt::OutputBuffer();
+  buffer->data.assign(data, data + size);
+  return buffer;
+}
+
+void wabt_errors_clear(wabt::Errors* errors) {
+  errors->clear();
+}
+
+size_t wabt_errors_get_count(wabt::Errors* errors) {
+  return errors->size();
+}
+
+const char* wabt_errors_get_message(wabt::Errors* errors, size_t index) {
+  return (*errors)[index].message.c_str();
+}
+
+int wabt_errors_get_level(wabt::Errors* errors, size_t index) {
+  return (*errors)[index].error_level == wabt::ErrorLevel::Warning ? 0 : 1;
+}
+
+int wabt_errors_has_text_location(wabt::Errors* errors, size_t index) {
+  // Binary readers report offsets and never set a filename.
+  return !(*errors)[index].loc.filename.empty();
+}
+
+int wabt_errors_get_line(wabt::Errors* errors, size_t index) {
+  return (*errors)[index].loc.line;
+}
+
+int wabt_errors_get_column(wabt::Errors* errors, size_t index) {
+  return (*errors)[index].loc.first_column;
+}
+
+size_t wabt_errors_get_offset(wabt::Errors* errors, size_t index) {
+  return (*errors)[index].loc.offset;
+}
+
 }